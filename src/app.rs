@@ -1,13 +1,59 @@
-use egui::{Color32, Pos2, Context, Key, TextStyle, FontId, Painter, Vec2, Rect, TextureOptions, vec2};
-use image::{self, Rgba, RgbaImage};
-use tinyfiledialogs::{MessageBoxIcon, OkCancel};
-use std::fs::{File, read};
-use std::io::{Write, Read};
-use std::path::Path;
 use egui::load::SizedTexture;
+use egui::{Color32, FontId, Pos2, Rect, Stroke, Vec2};
+use image::{self, Rgba, RgbaImage};
+use web_time::Instant;
 
 mod icons;
 use icons::*;
+mod tools;
+use tools::*;
+mod selection;
+use selection::*;
+mod transform;
+use transform::*;
+mod mirror;
+use mirror::*;
+mod symmetry;
+use symmetry::*;
+mod view;
+use view::*;
+mod layout;
+use layout::*;
+mod highlight;
+mod stats;
+mod validate;
+use validate::*;
+mod reference;
+mod remap;
+use reference::*;
+mod palette;
+use palette::*;
+mod ramps;
+use ramps::*;
+mod preview;
+use preview::*;
+mod history;
+use history::*;
+mod shortcuts;
+use shortcuts::*;
+mod recent;
+use recent::*;
+mod recovery;
+use recovery::*;
+mod dropped;
+mod platform;
+mod unsaved;
+use platform::{FileIo, OpenPurpose};
+mod toasts;
+use toasts::*;
+mod confirm;
+use confirm::*;
+mod textures;
+use textures::*;
+mod frame;
+use frame::*;
+mod jobs;
+use jobs::*;
 
 type ColorMatrix = Vec<Vec<Option<Color32>>>;
 type RefMatrix = Vec<Vec<Option<(usize, usize)>>>;
@@ -18,17 +64,38 @@ type RefMatrix = Vec<Vec<Option<(usize, usize)>>>;
 pub struct TemplateApp {
     //$ Save
     file_path: Option<String>,
+    canvas_view: PanelView,
+    reference_view: PanelView,
+    highlight_all_frames: bool,
+    show_heatmap: bool,
+    preview: Preview,
+    keymap: Keymap,
+    project_path: Option<String>,
+    recent_files: Vec<String>,
+    reopen_last: bool,
 
     //$ Not save
     #[serde(skip)]
     color_matrix: ColorMatrix,
     #[serde(skip)]
-    ref_matrix: Vec<RefMatrix>,
+    ref_matrix: Vec<Frame>,
+    #[serde(skip)]
+    remap_pairs: Vec<RemapPair>,
+    #[serde(skip)]
+    palettes: Vec<Palette>,
+    #[serde(skip)]
+    active_palette: Option<usize>,
+    #[serde(skip)]
+    ramps: Vec<Ramp>,
 
     //$ Helper data
     /*//# Icons
     #[serde(skip)]
     icons: Icon,*/
+    #[serde(skip)]
+    layout: Layout,
+    #[serde(skip)]
+    strip_scroll: StripScroll,
     //# Drag from canvas to ref mechanic
     #[serde(skip)]
     start_drag: Option<Pos2>,
@@ -42,59 +109,222 @@ pub struct TemplateApp {
     drag_ref: Option<(usize, usize)>,
     #[serde(skip)]
     drag_where: u8,
-    //# Frame and animations mechanism
+    //# Canvas tools
     #[serde(skip)]
-    refresh_rate_fps: u64,
+    tool: Tool,
     #[serde(skip)]
-    current_frame: usize,
+    selected_ref: Option<(usize, usize)>,
+    #[serde(skip)]
+    tool_anchor: Option<(usize, usize)>,
+    #[serde(skip)]
+    tool_corner: Option<(usize, usize)>,
+    #[serde(skip)]
+    symmetry: Symmetry,
+    #[serde(skip)]
+    symmetry_remap: bool,
+    //# Selection and clipboard
+    #[serde(skip)]
+    selection: Option<Selection>,
+    #[serde(skip)]
+    lasso_path: Vec<(usize, usize)>,
+    #[serde(skip)]
+    floating: Option<Floating>,
+    #[serde(skip)]
+    float_grab: Option<(i32, i32)>,
+    #[serde(skip)]
+    clipboard: Option<String>,
+    #[serde(skip)]
+    nudge_wrap: bool,
+    //# Mirrored frames
+    #[serde(skip)]
+    show_mirror: bool,
+    #[serde(skip)]
+    mirror_frames: Vec<bool>,
+    #[serde(skip)]
+    show_stats: bool,
+    //# Validation
+    #[serde(skip)]
+    problems: Vec<Problem>,
+    #[serde(skip)]
+    show_validation: bool,
+    //# Remapping after the reference changed
+    #[serde(skip)]
+    show_remap: bool,
+    #[serde(skip)]
+    remap_region: RefRegion,
+    #[serde(skip)]
+    remap_offset: (i32, i32),
+    #[serde(skip)]
+    remap_result: String,
+    //# Reference editing
+    #[serde(skip)]
+    show_reference_color: bool,
+    #[serde(skip)]
+    reference_modified: bool,
+    /// Reference pixels as loaded or last saved, written back as they were unless edited
+    #[serde(skip)]
+    reference_pixels: RgbaImage,
+    #[serde(skip)]
+    show_palettes: bool,
+    //# Shading ramps
     #[serde(skip)]
-    is_animating: bool,
+    show_ramps: bool,
     #[serde(skip)]
-    last_update: std::time::Instant,
+    ramp_row: (usize, usize, usize),
     #[serde(skip)]
-    accumulated_time: std::time::Duration,
+    shaded_cells: Vec<(usize, usize)>,
+    //# Undo and shortcuts
+    #[serde(skip)]
+    history: History,
+    #[serde(skip)]
+    show_keymap: bool,
+    #[serde(skip)]
+    rebinding: Option<Action>,
+    //# File access
+    #[serde(skip)]
+    file_io: FileIo,
+    //# Unsaved changes
+    /// Project as last saved or opened, its frames sharing buffers with the open ones until edited
+    #[serde(skip)]
+    saved: Option<PxRefFile>,
+    #[serde(skip)]
+    window_title: String,
+    #[serde(skip)]
+    allow_close: bool,
+    //# Notifications and confirmations
+    #[serde(skip)]
+    toasts: Toasts,
+    #[serde(skip)]
+    confirm: Option<Confirm>,
+    //# Cached textures
+    #[serde(skip)]
+    icons: Option<Icon>,
+    #[serde(skip)]
+    canvas_texture: GridTexture,
+    #[serde(skip)]
+    reference_texture: GridTexture,
+    #[serde(skip)]
+    frame_textures: Vec<GridTexture>,
+    #[serde(skip)]
+    preview_texture: GridTexture,
+    /// Bumped whenever the reference colors or the active palette change, so textures redraw
+    #[serde(skip)]
+    colors_revision: u64,
+    //# Background jobs
+    #[serde(skip)]
+    jobs: Vec<Job>,
+    //# Autosave and recovery
+    #[serde(skip)]
+    last_autosave: Instant,
+    /// Project and colors revision of the last snapshot
+    #[serde(skip)]
+    autosaved: Option<(PxRefFile, u64)>,
+    #[serde(skip)]
+    snapshots: Vec<Snapshot>,
+    #[serde(skip)]
+    show_recovery: bool,
+    //# Frame and animations mechanism
+    #[serde(skip)]
+    show_preview: bool,
+    #[serde(skip)]
+    refresh_rate_fps: u64,
+    #[serde(skip)]
+    current_frame: usize,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 struct PxRefFile {
     ref_png: String,
-    ref_matrix: Vec<RefMatrix>,
-}
-
-impl PxRefFile {
-    fn save(&self) {
-        if let Some(mut render_path) = tinyfiledialogs::save_file_dialog("Save as", "") {
-            if !render_path.ends_with(".pxref") { render_path = format!("{}.pxref", render_path); }
-            if let Ok(mut file) = File::create(render_path) {
-                if let Ok(json) = serde_json::to_string_pretty(self) {
-                    file.write_all(json.as_bytes()).unwrap_or_else(|e| {
-                        tinyfiledialogs::message_box_ok("Failed to Save Ref", e.to_string().as_str(), MessageBoxIcon::Error);
-                    });
-                }
-            } else {
-                tinyfiledialogs::message_box_ok("Failed to Save Ref", "", MessageBoxIcon::Error);
-            }
-        }
-    }
+    ref_matrix: Vec<Frame>,
+    #[serde(default)]
+    remap_pairs: Vec<RemapPair>,
+    #[serde(default)]
+    palettes: Vec<Palette>,
+    #[serde(default)]
+    ramps: Vec<Ramp>,
+    /// Hex encoded reference PNG, for projects saved where `ref_png` can't be read back (the web)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ref_png_data: Option<String>,
 }
 
 impl Default for TemplateApp {
     fn default() -> Self {
         Self {
             file_path: None,
+            canvas_view: PanelView::default(),
+            reference_view: PanelView::default(),
+            highlight_all_frames: false,
+            show_heatmap: false,
+            preview: Preview::default(),
+            keymap: Keymap::default(),
+            project_path: None,
+            recent_files: Vec::new(),
+            reopen_last: false,
             color_matrix: vec![vec![None; 16]; 16],
-            ref_matrix: vec![vec![vec![None; 16]; 16]],
+            ref_matrix: vec![Frame::new((16, 16))],
+            remap_pairs: Vec::new(),
+            palettes: Vec::new(),
+            active_palette: None,
+            ramps: Vec::new(),
+            layout: Layout::default(),
+            strip_scroll: StripScroll::default(),
             start_drag: None,
             end_drag: None,
             is_dragging: false,
             drag_color: None,
             drag_ref: None,
             current_frame: 0,
-            refresh_rate_fps: 1000/5, // 1000/fps : default 5 fps
-            drag_where: 2, // 2 is none
-            is_animating: false,
-            last_update: std::time::Instant::now(),
-            accumulated_time: std::time::Duration::from_millis(0),
+            refresh_rate_fps: 1000 / 5, // 1000/fps : default 5 fps
+            drag_where: 2,              // 2 is none
+            tool: Tool::Drag,
+            selected_ref: None,
+            tool_anchor: None,
+            tool_corner: None,
+            symmetry: Symmetry::None,
+            symmetry_remap: false,
+            selection: None,
+            lasso_path: Vec::new(),
+            floating: None,
+            float_grab: None,
+            clipboard: None,
+            nudge_wrap: false,
+            show_mirror: false,
+            mirror_frames: Vec::new(),
+            show_stats: false,
+            problems: Vec::new(),
+            show_validation: false,
+            show_remap: false,
+            remap_region: RefRegion::default(),
+            remap_offset: (0, 0),
+            remap_result: String::new(),
+            show_reference_color: false,
+            reference_modified: false,
+            reference_pixels: RgbaImage::default(),
+            show_palettes: false,
+            show_ramps: false,
+            ramp_row: (0, 0, 4),
+            shaded_cells: Vec::new(),
+            history: History::new(&[Frame::new((16, 16))]),
+            show_keymap: false,
+            rebinding: None,
+            file_io: FileIo::default(),
+            saved: None,
+            window_title: String::new(),
+            allow_close: false,
+            toasts: Toasts::default(),
+            confirm: None,
+            icons: None,
+            canvas_texture: GridTexture::default(),
+            reference_texture: GridTexture::default(),
+            frame_textures: Vec::new(),
+            preview_texture: GridTexture::default(),
+            colors_revision: 0,
+            jobs: Vec::new(),
+            last_autosave: Instant::now(),
+            autosaved: None,
+            snapshots: Vec::new(),
+            show_recovery: false,
+            show_preview: false,
         }
     }
 }
@@ -107,14 +337,277 @@ impl TemplateApp {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
+        let mut app: TemplateApp = Default::default();
         if let Some(storage) = cc.storage {
-            let mut stored_state: TemplateApp = eframe::get_value(storage, eframe::APP_KEY).unwrap_or(Default::default());
-            if let Some(file_path) = &stored_state.file_path {
-                stored_state.color_matrix = parse_png_to_matrix(&file_path);
+            app = eframe::get_value(storage, eframe::APP_KEY).unwrap_or(Default::default());
+            // A reopened project brings its own reference. Browsers can't read the PNG back from
+            // its path, the stored projects carry it instead.
+            let reopened = app.reopen_last_project(&cc.egui_ctx);
+            if !reopened && !cfg!(target_arch = "wasm32") {
+                if let Some(file_path) = app.file_path.clone() {
+                    let job = Job::new(
+                        &cc.egui_ctx,
+                        &format!("Opening {}", file_name(&file_path)),
+                        move |_| {
+                            let reference = ReferenceImage::new(
+                                read_png(&file_path).map_err(|e| e.to_string())?,
+                            );
+                            Ok(Done::Reference {
+                                name: file_path,
+                                path: None,
+                                reference,
+                            })
+                        },
+                    );
+                    app.start_job(job);
+                }
+            }
+        }
+        app.check_recovery();
+        app
+    }
+
+    fn frame_size(&self) -> (usize, usize) {
+        self.ref_matrix[self.current_frame].size()
+    }
+
+    fn reference_size(&self) -> (usize, usize) {
+        (
+            self.color_matrix.len(),
+            self.color_matrix.first().map_or(0, |col| col.len()),
+        )
+    }
+
+    /// Color of a reference pixel in the active palette, `None` if it's transparent or outside
+    /// the reference
+    fn ref_color(&self, pos: (usize, usize)) -> Option<Color32> {
+        palette_color(
+            &self.color_matrix,
+            self.active_palette.and_then(|k| self.palettes.get(k)),
+            pos,
+        )
+    }
+
+    /// Color a frame cell is drawn with, the checkerboard where it's empty
+    fn cell_color(&self, frame: &Frame, x: usize, y: usize) -> Color32 {
+        frame
+            .get(x, y)
+            .and_then(|pos| self.ref_color(pos))
+            .unwrap_or_else(|| get_checkerboard(x, y))
+    }
+
+    /// Renders the sheet in the background and writes it to a file the user picks
+    fn save_image(&mut self, ctx: &egui::Context) {
+        let Some(render_path) = platform::ask_save_path("Render as", "sheet.png") else {
+            return;
+        };
+        let (frames, color_matrix) = (self.ref_matrix.clone(), self.color_matrix.clone());
+        let palette = self
+            .active_palette
+            .and_then(|k| self.palettes.get(k))
+            .cloned();
+        let job = Job::new(ctx, "Rendering sheet", move |_| {
+            let bytes = encode_png(&render_sheet(&frames, &color_matrix, palette.as_ref()))?;
+            platform::write_file(&with_extension(render_path, "png"), &bytes)?;
+            Ok(Done::Exported { files: 1 })
+        });
+        self.start_job(job);
+    }
+
+    /// Asks for a .pxref file, it's loaded with its reference PNG once read
+    fn load_ref(&mut self, ctx: &egui::Context) {
+        self.after_discard(ctx, Deferred::LoadRef);
+    }
+
+    /// Handles the files picked through `file_io` since the last frame
+    fn update_opened_files(&mut self, ctx: &egui::Context) {
+        for file in self.file_io.take_opened() {
+            let file = match file {
+                Ok(file) => file,
+                Err(e) => {
+                    self.notify(Level::Error, "Unable to open file", &e);
+                    continue;
+                }
+            };
+            let title = format!("Opening {}", file_name(&file.name));
+            let job = match file.purpose {
+                OpenPurpose::Reference => Job::new(ctx, &title, move |_| {
+                    let reference =
+                        ReferenceImage::new(decode_png(&file.bytes).map_err(|e| e.to_string())?);
+                    Ok(Done::Reference {
+                        name: file.name,
+                        path: file.path,
+                        reference,
+                    })
+                }),
+                OpenPurpose::Project => Job::new(ctx, &title, move |_| {
+                    let (data, reference) = parse_project(&String::from_utf8_lossy(&file.bytes))?;
+                    Ok(Done::Project {
+                        name: Some(file.name),
+                        path: file.path,
+                        data,
+                        reference,
+                        snapshot: false,
+                    })
+                }),
+                OpenPurpose::OldReference => Job::new(ctx, &title, move |_| {
+                    let old = decode_png_to_matrix(&file.bytes).map_err(|e| e.to_string())?;
+                    Ok(Done::OldReference(old))
+                }),
+            };
+            self.start_job(job);
+        }
+    }
+
+    /// Loads a .pxref project from disk in the background, forgetting it from the recent files
+    /// if it can't be read
+    fn open_ref(&mut self, ctx: &egui::Context, path: &str) {
+        let path = path.to_string();
+        let job = Job::new(ctx, &format!("Opening {}", file_name(&path)), {
+            let path = path.clone();
+            move |_| {
+                let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                let (data, reference) = parse_project(&json)?;
+                Ok(Done::Project {
+                    name: Some(path.clone()),
+                    path: Some(path),
+                    data,
+                    reference,
+                    snapshot: false,
+                })
+            }
+        });
+        self.start_job(job.forget_on_failure(Some(path)));
+    }
+
+    /// Opens a project saved as `name` in the background
+    fn load_project_json(&mut self, ctx: &egui::Context, json: String, name: &str) {
+        let name = name.to_string();
+        let job = Job::new(ctx, &format!("Opening {}", file_name(&name)), move |_| {
+            let (data, reference) = parse_project(&json)?;
+            Ok(Done::Project {
+                name: Some(name),
+                path: None,
+                data,
+                reference,
+                snapshot: false,
+            })
+        });
+        self.start_job(job);
+    }
+
+    /// Replaces the project with `data`, `reference` being its decoded reference PNG
+    fn apply_project(&mut self, data: PxRefFile, reference: ReferenceImage) {
+        self.file_path = Some(data.ref_png);
+        self.color_matrix = reference.matrix;
+        self.reference_pixels = reference.pixels;
+        self.reference_modified = false;
+        self.ref_matrix = data.ref_matrix;
+        self.remap_pairs = data.remap_pairs;
+        self.palettes = data.palettes;
+        self.ramps = data.ramps;
+        self.active_palette = None;
+        self.colors_revision += 1;
+        self.current_frame = 0;
+        self.history = History::new(&self.ref_matrix);
+        self.run_validation();
+    }
+
+    /// Loads a reference PNG in the background, keeping the frames
+    fn open_png(&mut self, ctx: &egui::Context, path: &str) {
+        let path = path.to_string();
+        let job = Job::new(ctx, &format!("Opening {}", file_name(&path)), {
+            let path = path.clone();
+            move |_| {
+                let reference = ReferenceImage::new(read_png(&path).map_err(|e| e.to_string())?);
+                Ok(Done::Reference {
+                    name: path.clone(),
+                    path: Some(path),
+                    reference,
+                })
             }
-            return stored_state;
+        });
+        self.start_job(job.forget_on_failure(Some(path)));
+    }
+
+    fn project_file(&self) -> PxRefFile {
+        PxRefFile {
+            ref_png: self.file_path.clone().unwrap_or_default(),
+            ref_matrix: self.ref_matrix.clone(),
+            remap_pairs: self.remap_pairs.clone(),
+            palettes: self.palettes.clone(),
+            ramps: self.ramps.clone(),
+            ref_png_data: None,
+        }
+    }
+
+    fn set_reference(&mut self, path: &str, reference: ReferenceImage) {
+        self.file_path = Some(path.to_string());
+        self.color_matrix = reference.matrix;
+        self.reference_pixels = reference.pixels;
+        self.reference_modified = false;
+        self.colors_revision += 1;
+        self.run_validation();
+    }
+
+    /// Saves to the current project file, asking for one if there's none yet. True if saved.
+    fn save_ref(&mut self) -> bool {
+        match self.project_path.clone() {
+            Some(path) => self.write_ref(&path, false),
+            None => self.save_ref_as(),
         }
-        Default::default()
+    }
+
+    fn save_ref_as(&mut self) -> bool {
+        match platform::ask_save_path("Save as", "project.pxref") {
+            Some(path) => self.write_ref(&with_extension(path, "pxref"), true),
+            None => false,
+        }
+    }
+
+    /// On the web saving again only updates the copy in browser storage, Save As also downloads it
+    fn write_ref(&mut self, path: &str, save_as: bool) -> bool {
+        if self.file_path.is_none() {
+            self.notify(
+                Level::Error,
+                "Failed to Save Ref",
+                "Load a reference PNG first",
+            );
+            return false;
+        }
+        let is_web = cfg!(target_arch = "wasm32");
+        let mut data = self.project_file();
+        if is_web {
+            match encode_png(&matrix_to_image(&self.color_matrix, &self.reference_pixels)) {
+                Ok(bytes) => data.ref_png_data = Some(platform::to_hex(&bytes)),
+                Err(e) => {
+                    self.notify(Level::Error, "Failed to Save Ref", &e);
+                    return false;
+                }
+            }
+        }
+        let json = match serde_json::to_string_pretty(&data) {
+            Ok(json) => json,
+            Err(e) => {
+                self.notify(Level::Error, "Failed to Save Ref", &e.to_string());
+                return false;
+            }
+        };
+        if let Err(e) = platform::store_project(path, &json) {
+            self.notify(Level::Error, "Failed to store project", &e);
+        }
+        if !is_web || save_as {
+            if let Err(e) = platform::write_file(path, json.as_bytes()) {
+                self.notify(Level::Error, "Failed to Save Ref", &e);
+                return false;
+            }
+        }
+        self.project_path = Some(path.to_string());
+        if !is_web {
+            push_recent(&mut self.recent_files, path);
+        }
+        self.mark_saved();
+        true
     }
 }
 
@@ -124,7 +617,13 @@ impl eframe::App for TemplateApp {
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
         #[allow(non_snake_case)]
-        let ICON:Icon = load_icons(ctx);
+        let ICON: Icon = self.icons.get_or_insert_with(|| load_icons(ctx)).clone();
+        self.update_shortcuts(ctx);
+        self.update_close_request(ctx);
+        self.update_dropped_files(ctx);
+        self.update_opened_files(ctx);
+        self.update_jobs();
+        self.update_title(ctx);
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
@@ -134,105 +633,208 @@ impl eframe::App for TemplateApp {
                 let is_web = cfg!(target_arch = "wasm32");
                 ui.menu_button("File", |ui| {
                     if ui.button("Load PNG").clicked() {
-                        match tinyfiledialogs::open_file_dialog("Open", "", None) {
-                            Some(file) => {
-                                if file.ends_with(".png") {
-                                    self.file_path = Some(file.clone());
-                                    self.color_matrix = parse_png_to_matrix(&file);
-                                } else {
-                                    tinyfiledialogs::message_box_ok(
-                                        "Invalid File", "Please pick a .png file",
-                                        MessageBoxIcon::Error);
-                                    self.file_path = None;
-                                }
-                            },
-                            None => self.file_path = None,
-                        }
+                        self.file_io
+                            .open(ctx, OpenPurpose::Reference, "Open", "png");
+                        ui.close_menu();
                     }
-                    if ui.button("Load Ref").clicked() {
-                        match tinyfiledialogs::open_file_dialog("Open", "", None) {
-                            Some(path) => {
-                                if path.ends_with(".pxref") {
-                                    if let Ok(mut file) = File::open(path) {
-                                        let mut json_str = String::new();
-                                        if let Err(E) = file.read_to_string(&mut json_str) {
-                                            tinyfiledialogs::message_box_ok(
-                                                "Unable to open Ref", &E.to_string(),
-                                                MessageBoxIcon::Error);
-                                        };
-                                        if let Ok(parsed_data) = serde_json::from_str::<PxRefFile>(&json_str) {
-                                            self.file_path = Some(parsed_data.ref_png.clone());
-                                            self.color_matrix = parse_png_to_matrix(&parsed_data.ref_png);
-                                            self.ref_matrix = parsed_data.ref_matrix;
-                                            self.current_frame = 0;
-                                        } else {
-                                            tinyfiledialogs::message_box_ok(
-                                                "Unable to open Ref", "",
-                                                MessageBoxIcon::Error);
-                                        };
-                                    } else {
-                                        tinyfiledialogs::message_box_ok(
-                                            "Unable to open Ref", "",
-                                            MessageBoxIcon::Error);
-                                    };
-                                } else {
-                                    tinyfiledialogs::message_box_ok(
-                                        "Unable to open Ref", "Please pick a .pxref file",
-                                        MessageBoxIcon::Error);
-                                }
-
-                            }
-                            _ => {}
-                        }
+                    if ui
+                        .add(
+                            egui::Button::new("Load Ref")
+                                .shortcut_text(self.shortcut_text(ctx, Action::LoadRef)),
+                        )
+                        .clicked()
+                    {
+                        self.load_ref(ctx);
+                        ui.close_menu();
+                    }
+                    if is_web {
+                        self.stored_projects_menu(ui);
+                    } else {
+                        self.recent_menu(ui);
                     }
                     if ui.button("Save Image").clicked() {
-                        if let Some(mut render_path) = tinyfiledialogs::save_file_dialog("Render as", "") {
-                            let mut img = RgbaImage::new((16 * self.ref_matrix.len()) as u32, 16);
-                            // Renders all frames next to one another
-                            for (k, frame) in self.ref_matrix.iter().enumerate() {
-                                for (i, row) in frame.iter().enumerate() {
-                                    for (j, col_opt) in row.iter().enumerate() {
-                                        if let Some(pos) = col_opt {
-                                            let color = self.color_matrix[pos.0][pos.1];
-                                            if let Some(color) = color {
-                                                img.put_pixel(i as u32 + 16*k as u32, j as u32, Rgba([color[0], color[1], color[2], color[3]]));
-                                            } else {
-                                                img.put_pixel(i as u32 + 16*k as u32, j as u32, Rgba([0, 0, 0, 0]));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            if !render_path.ends_with(".png") {
-                                render_path = format!("{}.png", render_path);
-                            }
-                            img.save(render_path).unwrap_or_else(|e| {
-                                tinyfiledialogs::message_box_ok("Failed to Render Image", e.to_string().as_str(), MessageBoxIcon::Error);
-                            });
-                        }
+                        self.save_image(ctx);
+                        ui.close_menu();
                     }
-                    if ui.button("Save Ref").clicked() {
-                        let data = PxRefFile {
-                            ref_png: self.file_path.clone().unwrap(),
-                            ref_matrix: self.ref_matrix.clone(),
-                        };
-                        data.save();
+                    if ui.button("Export All Variants...").clicked() {
+                        self.export_all_variants(ctx);
+                        ui.close_menu();
                     }
-                    if !is_web {
-                        if ui.button("Quit").clicked() {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
+                    if ui
+                        .add(
+                            egui::Button::new("Save Ref")
+                                .shortcut_text(self.shortcut_text(ctx, Action::SaveRef)),
+                        )
+                        .clicked()
+                    {
+                        self.save_ref();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add(
+                            egui::Button::new("Save Ref As...")
+                                .shortcut_text(self.shortcut_text(ctx, Action::SaveRefAs)),
+                        )
+                        .clicked()
+                    {
+                        self.save_ref_as();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Save Reference PNG").clicked() {
+                        self.save_reference(false);
+                        ui.close_menu();
+                    }
+                    if ui.button("Save Reference PNG As...").clicked() {
+                        self.save_reference(true);
+                        ui.close_menu();
+                    }
+                    if !is_web && ui.button("Quit").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
                 ui.menu_button("Edit", |ui| {
+                    let undo = egui::Button::new("Undo")
+                        .shortcut_text(self.shortcut_text(ctx, Action::Undo));
+                    if ui.add_enabled(self.history.can_undo(), undo).clicked() {
+                        self.undo();
+                        ui.close_menu();
+                    }
+                    let redo = egui::Button::new("Redo")
+                        .shortcut_text(self.shortcut_text(ctx, Action::Redo));
+                    if ui.add_enabled(self.history.can_redo(), redo).clicked() {
+                        self.redo();
+                        ui.close_menu();
+                    }
+                    ui.separator();
                     if ui.button("Clear Canvas").clicked() {
-                        match tinyfiledialogs::message_box_ok_cancel("Clear Canvas", "Are you sure?", MessageBoxIcon::Error, OkCancel::Cancel) {
-                            OkCancel::Ok => {
-                                self.ref_matrix[self.current_frame] = vec![vec![None; 16]; 16];
+                        self.ask("Clear Canvas", "Are you sure?", Deferred::ClearCanvas);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Cut").clicked() {
+                        self.cut_selection(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.button("Copy").clicked() {
+                        self.copy_selection(ctx);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(self.clipboard.is_some(), egui::Button::new("Paste"))
+                        .clicked()
+                    {
+                        if let Some(text) = self.clipboard.clone() {
+                            self.paste(&text);
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Select All").clicked() {
+                        self.commit_floating();
+                        self.selection = Some(Selection::all(self.frame_size()));
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.selection.is_some() || self.floating.is_some(),
+                            egui::Button::new("Deselect"),
+                        )
+                        .clicked()
+                    {
+                        self.commit_floating();
+                        self.selection = None;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.menu_button("Transform", |ui| {
+                        for transform in Transform::ALL {
+                            if ui.button(transform.name()).clicked() {
+                                self.apply_transform(transform);
+                                ui.close_menu();
                             }
-                            OkCancel::Cancel => {}
                         }
+                        ui.separator();
+                        ui.checkbox(&mut self.nudge_wrap, "Wrap when nudging");
+                    });
+                    if ui.button("Edit Reference Color...").clicked() {
+                        self.show_reference_color = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Validate Project...").clicked() {
+                        self.run_validation();
+                        self.show_validation = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Remap References...").clicked() {
+                        self.show_remap = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Mirror Frames...").clicked() {
+                        self.show_mirror = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.separator();
+                for tool in Tool::ALL {
+                    ui.selectable_value(&mut self.tool, tool, tool.name());
+                }
+                ui.separator();
+                ui.menu_button("View", |ui| {
+                    if ui.button("Fit Canvas").clicked() {
+                        let size = self.frame_size();
+                        self.canvas_view.fit(self.layout.canvas.rect, size);
+                        ui.close_menu();
+                    }
+                    if ui.button("Fit Reference").clicked() {
+                        self.reference_view
+                            .fit(self.layout.reference.rect, self.reference_size());
+                        ui.close_menu();
+                    }
+                    if ui.button("Reset Zoom").clicked() {
+                        self.canvas_view.reset();
+                        self.reference_view.reset();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.checkbox(
+                        &mut self.highlight_all_frames,
+                        "Highlight hovered ref in all frames",
+                    );
+                    ui.checkbox(&mut self.show_heatmap, "Reference usage heatmap");
+                    if ui.button("Palettes...").clicked() {
+                        self.show_palettes = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Shading Ramps...").clicked() {
+                        self.show_ramps = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Animation Preview...").clicked() {
+                        self.show_preview = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Statistics...").clicked() {
+                        self.show_stats = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Keyboard Shortcuts...").clicked() {
+                        self.show_keymap = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.separator();
+                self.palette_selector(ui);
+                ui.menu_button("Symmetry", |ui| {
+                    for symmetry in Symmetry::ALL {
+                        ui.radio_value(&mut self.symmetry, symmetry, symmetry.name());
                     }
+                    ui.separator();
+                    ui.checkbox(
+                        &mut self.symmetry_remap,
+                        "Swap paired refs on mirrored side",
+                    )
+                    .on_hover_text("Uses the regions of Edit > Mirror Frames");
                 });
                 ui.add_space(16.0);
 
@@ -241,60 +843,163 @@ impl eframe::App for TemplateApp {
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            let square_size = 16; // Width and height
-            let start = egui::pos2(16.0, 16.0); // Top-left corner
+            self.update_layout(ui);
+            let frame_size = self.frame_size();
+            let reference_size = self.reference_size();
 
             let painter = ui.painter();
+            let canvas_painter = painter.with_clip_rect(self.layout.canvas.rect);
+            let reference_painter = painter.with_clip_rect(self.layout.reference.rect);
 
             //% left panel
-            for (x, row) in self.ref_matrix[self.current_frame].iter().enumerate() {
-                for (y, data) in row.iter().enumerate() {
-                    let pos = start + egui::vec2(0. + (x*square_size) as f32, 16. + (y*square_size) as f32);
-                    let color:Color32;
-                    let mut ref_num:Option<String> = None;
-                    if let Some(coords) = data {
-                        if let Some(color_) = self.color_matrix[coords.0][coords.1] {
-                            color = color_;
-                            ref_num = Some((coords.1*16 + coords.0 + 1).to_string());
+            let mut canvas_texture = std::mem::take(&mut self.canvas_texture);
+            let frame = &self.ref_matrix[self.current_frame];
+            canvas_texture.update(
+                ctx,
+                "canvas",
+                (frame.version(), self.colors_revision),
+                frame_size,
+                |x, y| self.cell_color(frame, x, y),
+            );
+            canvas_texture.paint(&canvas_painter, self.layout.canvas.grid_rect(frame_size));
+            self.canvas_texture = canvas_texture;
+            // Ref numbers and problem markers are few, only cells with something to show are visited
+            let label_size = self.layout.canvas.view.label_size();
+            for ((x, y), data) in self.ref_matrix[self.current_frame].cells() {
+                let Some(coords) = data else { continue };
+                let cell_rect = self.layout.canvas.cell_rect(x, y);
+                if let (Some(color), Some(label_size)) = (self.ref_color(coords), label_size) {
+                    let ref_num = (coords.1 * 16 + coords.0 + 1).to_string();
+                    canvas_painter.text(
+                        cell_rect.min,
+                        egui::Align2::LEFT_TOP,
+                        ref_num,
+                        egui::FontId::new(label_size, egui::FontFamily::Proportional),
+                        if color == Color32::WHITE {
+                            Color32::GRAY
                         } else {
-                            color = get_checkerboard(x, y);
-                        }
-                    } else {
-                        color = get_checkerboard(x, y);
-                    }
-                    painter.rect_filled(
-                        egui::Rect::from_min_size(pos, egui::vec2(square_size as f32, square_size as f32)),
-                        0.0,    // Corner rounding (0 for a square)
-                        color,
+                            Color32::WHITE
+                        },
                     );
-                    if let Some(ref_num) = ref_num {
-                        painter.text(pos, egui::Align2::LEFT_TOP, ref_num,
-                                     egui::FontId::new(6.0, egui::FontFamily::Proportional),
-                                     if color == Color32::WHITE { Color32::GRAY } else { Color32::WHITE });
-                    }
+                }
+                if let Some(kind) = check_ref(&self.color_matrix, coords) {
+                    paint_problem(&canvas_painter, cell_rect, kind);
                 }
             }
 
             //% Right panel
-            for (x, row) in self.color_matrix.iter_mut().enumerate() {
-                for (y, col) in row.iter_mut().enumerate() {
-                    let pos = start + egui::vec2(272. + (x*square_size) as f32, 16. + (y*square_size) as f32);
-                    let color:Color32;
-                    if let Some(color_) = col {
-                        color = *color_;
-                    } else {
-                        color = get_checkerboard(x, y);
+            let mut reference_texture = std::mem::take(&mut self.reference_texture);
+            reference_texture.update(
+                ctx,
+                "reference",
+                self.colors_revision,
+                reference_size,
+                |x, y| {
+                    self.ref_color((x, y))
+                        .unwrap_or_else(|| get_checkerboard(x, y))
+                },
+            );
+            reference_texture.paint(
+                &reference_painter,
+                self.layout.reference.grid_rect(reference_size),
+            );
+            self.reference_texture = reference_texture;
+
+            if let Some((x, y)) = self.selected_ref {
+                reference_painter.rect_stroke(
+                    self.layout.reference.cell_rect(x, y),
+                    0.0,
+                    Stroke::new(2.0, Color32::YELLOW),
+                );
+            }
+
+            if self.show_heatmap {
+                self.paint_heatmap(&reference_painter);
+            }
+
+            let hovered_ref = self.hovered_ref(ctx);
+            if let Some(hovered) = hovered_ref {
+                self.paint_hover_highlights(hovered, &canvas_painter, &reference_painter);
+            }
+
+            //$ Fill tools
+            let pointer = ctx.input(|i| i.pointer.latest_pos());
+            let hovered_cell =
+                pointer.and_then(|pos| self.layout.canvas_cell_at(ctx, pos, frame_size));
+            if ctx.input(|i| i.pointer.primary_clicked()) {
+                if let Some(cell) =
+                    pointer.and_then(|pos| self.layout.reference_cell_at(ctx, pos, reference_size))
+                {
+                    self.selected_ref = Some(cell);
+                    if ctx.input(|i| {
+                        i.pointer
+                            .button_double_clicked(egui::PointerButton::Primary)
+                    }) {
+                        self.show_reference_color = true;
                     }
-                    painter.rect_filled(
-                        egui::Rect::from_min_size(pos, egui::vec2(square_size as f32, square_size as f32)),
-                        0.0,    // Corner rounding (0 for a square)
-                        color,
-                    );
                 }
             }
+            // Shift fills with nothing, clearing the region
+            let fill_ref = if ctx.input(|i| i.modifiers.shift) {
+                None
+            } else {
+                self.selected_ref
+            };
+            let can_fill = fill_ref.is_some() || ctx.input(|i| i.modifiers.shift);
+            match self.tool {
+                Tool::Drag | Tool::Select | Tool::Lasso | Tool::Shade => {}
+                Tool::Bucket => {
+                    if let Some(cell) = hovered_cell {
+                        if can_fill && ctx.input(|i| i.pointer.primary_clicked()) {
+                            self.place_flood_fill(cell, fill_ref);
+                        }
+                    }
+                }
+                Tool::Rectangle => {
+                    if ctx.input(|i| i.pointer.primary_pressed()) {
+                        self.tool_anchor = hovered_cell;
+                        self.tool_corner = hovered_cell;
+                    }
+                    if hovered_cell.is_some() && self.tool_anchor.is_some() {
+                        self.tool_corner = hovered_cell;
+                    }
+                    if let (Some(anchor), Some(corner)) = (self.tool_anchor, self.tool_corner) {
+                        if ctx.input(|i| i.pointer.primary_released()) {
+                            if can_fill {
+                                self.place_rect(anchor, corner, fill_ref);
+                            }
+                            self.tool_anchor = None;
+                            self.tool_corner = None;
+                        } else {
+                            canvas_painter.rect_stroke(
+                                self.layout
+                                    .canvas
+                                    .cell_rect(anchor.0.min(corner.0), anchor.1.min(corner.1))
+                                    .union(
+                                        self.layout.canvas.cell_rect(
+                                            anchor.0.max(corner.0),
+                                            anchor.1.max(corner.1),
+                                        ),
+                                    ),
+                                0.0,
+                                Stroke::new(2.0, Color32::YELLOW),
+                            );
+                        }
+                    }
+                }
+            }
+
+            self.symmetry
+                .paint_axes(&canvas_painter, &self.layout.canvas, frame_size);
+            self.update_transform_keys(ctx);
+            self.update_selection(ctx, &canvas_painter);
+            self.update_shading(ctx, hovered_cell);
 
             //$ Mouse Drag Logic
-            if ctx.input(|i| i.pointer.is_decidedly_dragging()) {
+            let drag_size = Vec2::splat(self.layout.canvas.cell_size());
+            if self.tool == Tool::Drag
+                && ctx.input(|i| i.pointer.is_decidedly_dragging() && !i.pointer.middle_down())
+            {
                 if let Some(start) = ctx.input(|i| i.pointer.press_origin()) {
                     self.is_dragging = true;
                     self.start_drag = Some(start);
@@ -310,55 +1015,43 @@ impl eframe::App for TemplateApp {
             //println!("{:?}, {:?} -- {}", self.start_drag, self.end_drag, self.is_dragging);
             if let Some(start_drag) = self.start_drag {
                 //# Dragging on the right
-                if start_drag.x > 32. + 16.*square_size as f32{
+                if self.layout.reference.rect.contains(start_drag) {
                     self.drag_where = 1;
-                    let mut start_pos:Pos2 = start_drag;
-                    start_pos.x -= 32. + 16.*square_size as f32;
-                    start_pos.y -= 32.;
-                    let mut start_ints:(usize, usize) = (start_pos.x as usize, start_pos.y as usize);
-                    start_ints.0 = start_ints.0/16;
-                    start_ints.1 = start_ints.1/16;
-                    self.drag_ref = Some((start_ints.0, start_ints.1));
-                    if start_ints.0 < 16 && start_ints.1 < 16 {
-                        self.drag_color = self.color_matrix[start_ints.0][start_ints.1];
-                    }
-                } else { //# Dragging on the left
+                    self.drag_ref = self
+                        .layout
+                        .reference_cell_at(ctx, start_drag, reference_size);
+                    if let Some((x, y)) = self.drag_ref {
+                        self.drag_color = self.ref_color((x, y));
+                    }
+                } else {
+                    //# Dragging on the left
                     //self.drag_color = None;
                     self.drag_where = 0;
-                    let mut start_pos:Pos2 = start_drag;
-                    start_pos.x -= 16.;
-                    start_pos.y -= 32.;
-                    let mut start_ints:(usize, usize) = (start_pos.x as usize, start_pos.y as usize);
-                    start_ints.0 = start_ints.0/16;
-                    start_ints.1 = start_ints.1/16;
-                    self.drag_ref = Some((start_ints.0, start_ints.1));
-                    if start_ints.0 < 16 && start_ints.1 < 16 {
-                        if let Some(ref_indices) = self.ref_matrix[self.current_frame][start_ints.0][start_ints.1] {
-                            self.drag_color = self.color_matrix[ref_indices.0][ref_indices.1];
+                    self.drag_ref = self.layout.canvas_cell_at(ctx, start_drag, frame_size);
+                    if let Some((x, y)) = self.drag_ref {
+                        if let Some(ref_indices) = self.ref_matrix[self.current_frame].get(x, y) {
+                            self.drag_color = self.ref_color(ref_indices);
                         }
                     }
                 }
-            } else { self.drag_color = None; self.drag_where = 2; }
+            } else {
+                self.drag_color = None;
+                self.drag_where = 2;
+            }
 
-            if self.is_dragging == true && self.drag_where == 1{
+            if self.is_dragging && self.drag_where == 1 {
                 if let Some(color) = self.drag_color {
                     painter.rect_filled(
-                        egui::Rect::from_min_size(self.end_drag.unwrap() - egui::vec2(8., 8.), egui::vec2(square_size as f32, square_size as f32)),
+                        egui::Rect::from_center_size(self.end_drag.unwrap(), drag_size),
                         0.0,
                         color,
                     );
                 }
-            } else if self.drag_where == 1 && self.is_dragging == false {
+            } else if self.drag_where == 1 && !self.is_dragging {
                 if let Some(end_drag) = self.end_drag {
                     if let Some(drag_ref) = self.drag_ref {
-                        let mut start_pos:Pos2 = end_drag;
-                        start_pos.x -= 16.;
-                        start_pos.y -= 32.;
-                        let mut start_ints:(usize, usize) = (start_pos.x as usize, start_pos.y as usize);
-                        start_ints.0 = start_ints.0/16;
-                        start_ints.1 = start_ints.1/16;
-                        if start_ints.0 < 16 && start_ints.1 < 16 {
-                            self.ref_matrix[self.current_frame][start_ints.0][start_ints.1] = Some(drag_ref);
+                        if let Some(cell) = self.layout.canvas_cell_at(ctx, end_drag, frame_size) {
+                            self.place_ref(cell, Some(drag_ref));
                         }
                         self.start_drag = None;
                         self.end_drag = None;
@@ -368,57 +1061,52 @@ impl eframe::App for TemplateApp {
                     }
                 }
             }
-            if self.is_dragging == true && self.drag_where == 0 {
-                if let Some(mut latest) = self.end_drag {
-                    latest.x -= 16.;
-                    latest.y -= 32.;
-                    let (xc, yc) = (latest.x as usize / 16, latest.y as usize / 16);
-                    if xc < 16 && yc < 16 {
-                        if ui.input(|i| i.modifiers.shift) { // Mass delete
-                            self.ref_matrix[self.current_frame][xc][yc] = None;
+            if self.is_dragging && self.drag_where == 0 {
+                if let Some(latest) = self.end_drag {
+                    if let Some((xc, yc)) = self.layout.canvas_cell_at(ctx, latest, frame_size) {
+                        if ui.input(|i| i.modifiers.shift) {
+                            // Mass delete
+                            self.place_ref((xc, yc), None);
                             self.drag_where = 2;
                         } else if ui.input(|i| i.modifiers.ctrl)
-                            || ui.input(|i| i.modifiers.mac_cmd) { // Reorder
-                            if let Some(start) = self.start_drag {
-                                let (xc, yc) = ((start.x - 16.) as usize /16, (start.y - 32.) as usize / 16);
-                                //self.ref_matrix[self.current_frame][xc][yc] = None;
+                            || ui.input(|i| i.modifiers.mac_cmd)
+                        {
+                            // Reorder
+                            if let Some((xs, ys)) = self.drag_ref {
                                 // cover up so it looks like it is actually being dragged, not copied
-                                // this has a little visual bug but for the most part it's fine
-                                painter.rect_filled(
-                                    Rect::from_min_size(Pos2::new((xc + 1) as f32 * 16., (yc + 2) as f32 * 16.), vec2(square_size as f32, square_size as f32)),
+                                canvas_painter.rect_filled(
+                                    self.layout.canvas.cell_rect(xs, ys),
                                     0.0,
-                                    get_checkerboard(xc, yc),
+                                    get_checkerboard(xs, ys),
                                 );
                             }
                             if let Some(color) = self.drag_color {
                                 painter.rect_filled(
-                                    egui::Rect::from_min_size(self.end_drag.unwrap() - egui::vec2(8., 8.), egui::vec2(square_size as f32, square_size as f32)),
+                                    egui::Rect::from_center_size(latest, drag_size),
                                     0.0,
                                     color,
                                 );
                             }
                             self.drag_where = 0;
                         } else { // Copy from right
-                            //? Turned off this feature temporarily, as I feel it can lead to user-errors
-                            //? will turn back on when I implement ctrl-z
-                            //self.ref_matrix[self.current_frame][xc][yc] = Some((xc, yc));
-                            //self.drag_where = 2;
+                             //? Turned off this feature temporarily, as I feel it can lead to user-errors
+                             //? will turn back on when I implement ctrl-z
+                             //self.ref_matrix[self.current_frame][xc][yc] = Some((xc, yc));
+                             //self.drag_where = 2;
                         }
                     }
                 }
-            } else if self.drag_where == 0 && self.is_dragging == false {
-                if ui.input(|i| i.modifiers.ctrl)
-                    || ui.input(|i| i.modifiers.mac_cmd) {
+            } else if self.drag_where == 0 && !self.is_dragging {
+                if ui.input(|i| i.modifiers.ctrl) || ui.input(|i| i.modifiers.mac_cmd) {
                     if let Some(end_drag) = self.end_drag {
                         if let Some(drag_ref) = self.drag_ref {
-                            let (xc, yc) = ((end_drag.x - 16.) as usize / 16, (end_drag.y - 32.) as usize / 16);
-                            if xc < 16 && yc < 16 {
-                                self.ref_matrix[self.current_frame][xc][yc] = self.ref_matrix[self.current_frame][drag_ref.0][drag_ref.1];
-                            }
-                            if let Some(start_drag) = self.start_drag {
-                                let (xs, ys) = ((start_drag.x - 16.) as usize / 16, (start_drag.y - 32.) as usize / 16);
-                                if xc < 16 && yc < 16 {
-                                    self.ref_matrix[self.current_frame][xs][ys] = None;
+                            if let Some((xc, yc)) =
+                                self.layout.canvas_cell_at(ctx, end_drag, frame_size)
+                            {
+                                let frame = &mut self.ref_matrix[self.current_frame];
+                                frame.set(xc, yc, frame.get(drag_ref.0, drag_ref.1));
+                                if (xc, yc) != drag_ref {
+                                    frame.set(drag_ref.0, drag_ref.1, None);
                                 }
                             }
                         }
@@ -431,25 +1119,29 @@ impl eframe::App for TemplateApp {
                 self.drag_where = 2;
             }
             // Eraser Left
-            if ctx.input(|i| i.pointer.any_pressed() && i.modifiers.shift) {
+            if self.tool == Tool::Drag
+                && ctx.input(|i| i.pointer.any_pressed() && i.modifiers.shift)
+            {
                 if let Some(pos) = ctx.input(|i| i.pointer.latest_pos()) {
-                    if pos.x < (16 + 16*16) as f32 {
-                        let coords = ((pos.x  - 16.) as usize / 16, (pos.y - 32.) as usize / 16);
-                        if coords.0 < 16 && coords.1 < 16 {
-                            self.ref_matrix[self.current_frame][coords.0][coords.1] = None;
-                        }
+                    if let Some(coords) = self.layout.canvas_cell_at(ctx, pos, frame_size) {
+                        self.place_ref(coords, None);
                     }
                 }
             }
 
             //$ Frames
             let frames_len = self.ref_matrix.len();
-            for j in 0..frames_len {
-                let rect = egui::Rect::from_min_size(
-                    egui::pos2(16.0 + (32*j + 16*j) as f32 , 304.0), // Top-left corner of the rectangle
-                    egui::vec2(32.0, 32.0),  // Width and height of the rectangle
-                );
-                let response = ui.interact(rect, ui.id().with(j), egui::Sense::click());
+            let strip_painter = ui.painter().with_clip_rect(self.layout.frames);
+            let mut frame_textures = std::mem::take(&mut self.frame_textures);
+            frame_textures.resize_with(frames_len, GridTexture::default);
+            for (j, texture) in frame_textures.iter_mut().enumerate() {
+                let rect = self.layout.frame_rect(j);
+                // Thumbnails past the end of the strip are clipped away, so they can't take clicks either
+                let visible = rect.intersect(self.layout.frames);
+                if !visible.is_positive() {
+                    continue;
+                }
+                let response = ui.interact(visible, ui.id().with(j), egui::Sense::click());
                 let color = if ui.input(|i| i.modifiers.shift) && response.hovered() {
                     Color32::RED
                 } else if self.current_frame == j {
@@ -459,110 +1151,217 @@ impl eframe::App for TemplateApp {
                 } else {
                     Color32::LIGHT_GRAY
                 };
-                ui.painter().rect_filled(rect, 0.0, color);
-                ui.painter().text(
-                    rect.center() - egui::vec2(8., 12.),
-                    egui::Align2::LEFT_TOP,
-                    &format!("{}", j+1),
-                    FontId::proportional(20.0),
+                strip_painter.rect_filled(rect, 0.0, color);
+                let frame = &self.ref_matrix[j];
+                texture.update(
+                    ctx,
+                    &format!("frame_{}", j),
+                    (frame.version(), self.colors_revision),
+                    frame.size(),
+                    |x, y| self.cell_color(frame, x, y),
+                );
+                texture.paint(&strip_painter, rect.shrink(3.0));
+                strip_painter.text(
+                    rect.right_bottom() - Vec2::splat(2.0),
+                    egui::Align2::RIGHT_BOTTOM,
+                    format!("{}", j + 1),
+                    FontId::proportional(12.0),
                     Color32::WHITE,
                 );
+                if let (Some(hovered), true) = (hovered_ref, self.highlight_all_frames) {
+                    self.paint_frame_highlight(hovered, j, rect, &strip_painter);
+                }
                 if response.clicked() {
                     self.current_frame = j;
                     if ui.input(|i| i.modifiers.shift) {
                         if frames_len != 1 {
-                            match tinyfiledialogs::message_box_ok_cancel
-                                ("Do you want to remove the frame", "This action can not be undone",
-                                 MessageBoxIcon::Warning, OkCancel::Cancel) {
-                                OkCancel::Ok => {
-                                    self.ref_matrix.remove(self.current_frame);
-                                    tinyfiledialogs::message_box_ok("Frame Removed", &format!("Removed frame {}", self.current_frame + 1), MessageBoxIcon::Info);
-                                    self.current_frame -= 1;
-                                }
-                                OkCancel::Cancel => {}
-                            }
+                            self.ask(
+                                "Do you want to remove the frame",
+                                "It can be brought back with Undo",
+                                Deferred::RemoveFrame(j),
+                            );
                         } else {
-                            tinyfiledialogs::message_box_ok("Invalid action", "Can not remove the only frame", MessageBoxIcon::Info);
+                            self.notify(
+                                Level::Info,
+                                "Invalid action",
+                                "Can not remove the only frame",
+                            );
                         }
                     }
                 }
             }
 
-            let rect = egui::Rect::from_min_size(
-                egui::pos2(16.0 + ((32 + 16)*frames_len) as f32 , 304.0), // Top-left corner of the rectangle
-                egui::vec2(32.0, 32.0),  // Width and height of the rectangle
-            );
-            let response = ui.interact(rect, ui.id().with("Add1"), egui::Sense::click());
-            let color = if response.hovered() { Color32::GRAY } else { Color32::LIGHT_GRAY };
-            ui.painter().rect_filled(rect, 0.0, color);
-            ui.painter().text(
-                rect.center() - egui::vec2(8., 12.),
-                egui::Align2::LEFT_TOP,
-                "+",
-                FontId::proportional(20.0),
-                Color32::WHITE,
-            );
-            if response.clicked() {
-                self.ref_matrix.push(vec![vec![None; 16]; 16]);
-                self.current_frame = frames_len;
+            self.frame_textures = frame_textures;
+
+            let rect = self.layout.frame_rect(frames_len);
+            let visible = rect.intersect(self.layout.frames);
+            if visible.is_positive() {
+                let response = ui.interact(visible, ui.id().with("Add1"), egui::Sense::click());
+                let color = if response.hovered() {
+                    Color32::GRAY
+                } else {
+                    Color32::LIGHT_GRAY
+                };
+                strip_painter.rect_filled(rect, 0.0, color);
+                strip_painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    "+",
+                    FontId::proportional(20.0),
+                    Color32::WHITE,
+                );
+                if response.clicked() {
+                    self.ref_matrix.push(Frame::new(self.frame_size()));
+                    self.current_frame = frames_len;
+                }
             }
 
             //$ Play animation
-            if self.is_animating {
-                let now = std::time::Instant::now();
-                let elapsed = now.duration_since(self.last_update);
-                self.accumulated_time += elapsed;
-
-                while self.accumulated_time.as_millis() >= self.refresh_rate_fps as u128 {
-                    if self.current_frame == frames_len - 1 {
-                        self.current_frame = 0;
-                    } else {
-                        self.current_frame += 1;
-                    }
-                    self.accumulated_time -= std::time::Duration::from_millis(self.refresh_rate_fps);
-                }
+            //? Fix button size (optional)
+            const ICON_BUTTON_SIZE: Vec2 = Vec2::new(24.0, 24.0); // Image size
 
-                self.last_update = now;
-                ctx.request_repaint();
+            let playing = self.show_preview && self.preview.playing;
+            if ui_with_image_button(
+                ui,
+                if !playing { &ICON.play } else { &ICON.pause },
+                self.layout.play_button,
+                ICON_BUTTON_SIZE,
+            ) {
+                self.toggle_playback();
             }
-                //? Fix button size (optional)
-            let button_size = Vec2::new(32.0, 32.0); // Button size (specified by icon size, not independent)
-            const ICON_BUTTON_SIZE:Vec2 = Vec2::new(24.0, 24.0); // Image size
-
-            if ui_with_image_button(ui, if !self.is_animating {&ICON.play} else {&ICON.pause}, Vec2::new(560., 32.), button_size, ICON_BUTTON_SIZE) {
-                if self.is_animating {
-                    self.is_animating = false
-                } else {self.is_animating = true} // switch
-                println!("Play/Pause button pressed");
-            }
-
         });
+
+        self.mirror_window(ctx);
+        self.stats_window(ctx);
+        self.validation_window(ctx);
+        self.remap_window(ctx);
+        self.reference_color_window(ctx);
+        self.palettes_window(ctx);
+        self.ramps_window(ctx);
+        self.preview_viewport(ctx);
+        self.keymap_window(ctx);
+        self.recovery_window(ctx);
+        self.confirm_modal(ctx);
+        self.jobs_window(ctx);
+        self.show_toasts(ctx);
+        self.record_history(ctx);
+        self.autosave();
     }
 
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.clear_recovery_if_saved();
+    }
+}
+
+/// Color of a reference pixel with `palette` applied, `None` if it's transparent or outside the
+/// reference
+fn palette_color(
+    color_matrix: &ColorMatrix,
+    palette: Option<&Palette>,
+    pos: (usize, usize),
+) -> Option<Color32> {
+    let base = color_matrix.get(pos.0).and_then(|col| col.get(pos.1))?;
+    match palette.and_then(|palette| palette.get(pos)) {
+        Some(color) => color,
+        None => *base,
+    }
+}
+
+/// Renders all frames next to one another, in slots as big as the largest frame
+fn render_sheet(
+    frames: &[Frame],
+    color_matrix: &ColorMatrix,
+    palette: Option<&Palette>,
+) -> RgbaImage {
+    let width = frames.iter().map(|frame| frame.size().0).max().unwrap_or(0);
+    let height = frames.iter().map(|frame| frame.size().1).max().unwrap_or(0);
+    let mut img = RgbaImage::new((width * frames.len()) as u32, height as u32);
+    for (k, frame) in frames.iter().enumerate() {
+        for ((i, j), cell) in frame.cells() {
+            if let Some(color) = cell.and_then(|pos| palette_color(color_matrix, palette, pos)) {
+                img.put_pixel(
+                    (i + width * k) as u32,
+                    j as u32,
+                    Rgba(color.to_srgba_unmultiplied()),
+                );
+            }
+        }
+    }
+    img
+}
+
+/// Parses a .pxref and decodes its reference PNG
+fn parse_project(json: &str) -> Result<(PxRefFile, ReferenceImage), String> {
+    let data: PxRefFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let reference = project_reference(&data)?;
+    Ok((data, reference))
+}
+
+/// Decodes a project's reference PNG, embedded or next to it
+fn project_reference(data: &PxRefFile) -> Result<ReferenceImage, String> {
+    let pixels = match &data.ref_png_data {
+        Some(hex) => platform::from_hex(hex)
+            .ok_or("Invalid embedded reference".to_string())
+            .and_then(|bytes| decode_png(&bytes).map_err(|e| e.to_string())),
+        None => read_png(&data.ref_png).map_err(|e| format!("Unable to open reference PNG: {}", e)),
+    }?;
+    Ok(ReferenceImage::new(pixels))
+}
+
+/// Last component of a path, for messages
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map_or(path.to_string(), |name| name.to_string_lossy().to_string())
 }
 
 // Transparent png checkerboard using LIGHTGRAY and GRAY
-fn get_checkerboard(x:usize, y:usize) -> Color32 {
-    if (x+y)%2 == 0 {
+fn get_checkerboard(x: usize, y: usize) -> Color32 {
+    if (x + y) % 2 == 0 {
         Color32::LIGHT_GRAY
     } else {
         Color32::GRAY
     }
 }
 
-fn parse_png_to_matrix(file_path: &str) -> Vec<Vec<Option<Color32>>> {
-    // Load the image from file
-    let img = image::ImageReader::open(file_path)
-        .expect("Failed to open image file")
-        .decode()
-        .expect("Failed to decode image");
+fn read_png(file_path: &str) -> image::ImageResult<RgbaImage> {
+    Ok(image::ImageReader::open(file_path)?.decode()?.to_rgba8())
+}
+
+/// Same as `read_png` for an image already in memory
+fn decode_png(bytes: &[u8]) -> image::ImageResult<RgbaImage> {
+    Ok(image::load_from_memory(bytes)?.to_rgba8())
+}
+
+fn decode_png_to_matrix(bytes: &[u8]) -> image::ImageResult<ColorMatrix> {
+    Ok(image_to_matrix(&decode_png(bytes)?))
+}
+
+fn encode_png(img: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut bytes),
+        image::ImageFormat::Png,
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Adds `.extension` to `path` unless it already ends with it
+fn with_extension(path: String, extension: &str) -> String {
+    if path.ends_with(&format!(".{}", extension)) {
+        path
+    } else {
+        format!("{}.{}", path, extension)
+    }
+}
 
-    // Convert image to RGBA8 format
-    let img = img.to_rgba8();
+fn image_to_matrix(img: &RgbaImage) -> ColorMatrix {
     let (width, height) = img.dimensions();
 
     // Create the pixel matrix
@@ -573,7 +1372,12 @@ fn parse_png_to_matrix(file_path: &str) -> Vec<Vec<Option<Color32>>> {
         for x in 0..width {
             // Extract RGBA values for each pixel
             let pixel_colors = img.get_pixel(x, y).0;
-            let pixel = Color32::from_rgba_unmultiplied(pixel_colors[0], pixel_colors[1], pixel_colors[2], pixel_colors[3]);
+            let pixel = Color32::from_rgba_unmultiplied(
+                pixel_colors[0],
+                pixel_colors[1],
+                pixel_colors[2],
+                pixel_colors[3],
+            );
             if pixel_colors[3] == 0 {
                 row.push(None)
             } else {
@@ -595,23 +1399,16 @@ fn transpose<T: Clone>(matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
     let col_len = matrix[0].len();
 
     (0..col_len)
-        .map(|col| {
-            (0..row_len)
-                .map(|row| matrix[row][col].clone())
-                .collect()
-        })
+        .map(|col| (0..row_len).map(|row| matrix[row][col].clone()).collect())
         .collect()
 }
 
 fn ui_with_image_button(
     ui: &mut egui::Ui,
     texture: &egui::TextureHandle,
-    position: Vec2,
-    button_size: Vec2,
+    rect: Rect,
     image_size: Vec2,
 ) -> bool {
-    // Define the button's rectangle
-    let rect = Rect::from_min_size(position.to_pos2(), button_size);
     //ui.painter().rect_filled(rect, egui::Rounding::ZERO, Color32::WHITE);
 
     // Create an ImageButton with the image texture
@@ -621,4 +1418,4 @@ fn ui_with_image_button(
 
     // Render the button at the specified position and return if it was clicked
     ui.put(rect, image_button).clicked()
-}
\ No newline at end of file
+}
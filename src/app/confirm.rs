@@ -0,0 +1,203 @@
+use super::{platform, Frame, Level, OpenPurpose, TemplateApp};
+
+/// Something waiting on the user's answer to a confirmation
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Deferred {
+    ClearCanvas,
+    RemoveFrame(usize),
+    /// Opens the file picker for a project
+    LoadRef,
+    /// Opens a project from disk
+    OpenProject(String),
+    /// Opens a project already read, e.g. dropped in the browser
+    OpenProjectJson {
+        json: String,
+        name: String,
+    },
+    OpenStored(String),
+    RestoreSnapshot(usize),
+    Quit,
+}
+
+/// Modal question, answered on a later frame so the render loop never blocks
+pub struct Confirm {
+    pub title: String,
+    pub message: String,
+    pub action: Deferred,
+    /// Asking about unsaved changes: offers to save them before going ahead
+    pub offer_save: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Choice {
+    /// Save first, then go ahead if saving worked
+    Save,
+    Proceed,
+    Cancel,
+}
+
+impl TemplateApp {
+    /// Asks before running `action`
+    pub(super) fn ask(&mut self, title: &str, message: &str, action: Deferred) {
+        self.confirm = Some(Confirm {
+            title: title.to_string(),
+            message: message.to_string(),
+            action,
+            offer_save: false,
+        });
+    }
+
+    /// Runs `action` right away, or once the user saves or discards the unsaved changes
+    pub(super) fn after_discard(&mut self, ctx: &egui::Context, action: Deferred) {
+        if !self.has_unsaved_changes() {
+            self.run_deferred(ctx, action);
+            return;
+        }
+        self.confirm = Some(Confirm {
+            title: "Unsaved Changes".to_string(),
+            message: "Save the changes to the project first?".to_string(),
+            action,
+            offer_save: true,
+        });
+    }
+
+    pub(super) fn resolve_confirm(&mut self, ctx: &egui::Context, choice: Choice) {
+        let Some(confirm) = self.confirm.take() else {
+            return;
+        };
+        let proceed = match choice {
+            Choice::Save => {
+                self.save_ref() && (!self.reference_modified || self.save_reference(false))
+            }
+            Choice::Proceed => true,
+            Choice::Cancel => false,
+        };
+        if proceed {
+            self.run_deferred(ctx, confirm.action);
+        }
+    }
+
+    pub(super) fn run_deferred(&mut self, ctx: &egui::Context, action: Deferred) {
+        match action {
+            Deferred::ClearCanvas => {
+                self.ref_matrix[self.current_frame] = Frame::new(self.frame_size());
+            }
+            Deferred::RemoveFrame(j) => {
+                if j < self.ref_matrix.len() && self.ref_matrix.len() > 1 {
+                    self.ref_matrix.remove(j);
+                    self.current_frame = j.saturating_sub(1);
+                    self.refresh_problems();
+                }
+            }
+            Deferred::LoadRef => self
+                .file_io
+                .open(ctx, OpenPurpose::Project, "Open", "pxref"),
+            Deferred::OpenProject(path) => self.open_ref(ctx, &path),
+            Deferred::OpenProjectJson { json, name } => self.load_project_json(ctx, json, &name),
+            Deferred::OpenStored(name) => match platform::load_stored_project(&name) {
+                Some(json) => self.load_project_json(ctx, json, &name),
+                None => self.notify(
+                    Level::Error,
+                    "Unable to open Ref",
+                    &format!("{} is no longer stored", name),
+                ),
+            },
+            Deferred::RestoreSnapshot(k) => self.restore_snapshot(ctx, k),
+            Deferred::Quit => {
+                self.allow_close = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    pub(super) fn confirm_modal(&mut self, ctx: &egui::Context) {
+        let Some(confirm) = &self.confirm else { return };
+        let mut choice = None;
+        let response = egui::Modal::new(egui::Id::new("confirm")).show(ctx, |ui| {
+            ui.set_max_width(320.);
+            ui.heading(&confirm.title);
+            ui.label(&confirm.message);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if confirm.offer_save {
+                    if ui.button("Save").clicked() {
+                        choice = Some(Choice::Save);
+                    }
+                    if ui.button("Discard").clicked() {
+                        choice = Some(Choice::Proceed);
+                    }
+                } else if ui.button("OK").clicked() {
+                    choice = Some(Choice::Proceed);
+                }
+                if ui.button("Cancel").clicked() {
+                    choice = Some(Choice::Cancel);
+                }
+            });
+        });
+        // Escape and clicking outside cancel
+        if response.should_close() && choice.is_none() {
+            choice = Some(Choice::Cancel);
+        }
+        if let Some(choice) = choice {
+            self.resolve_confirm(ctx, choice);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// App with a ref in the first cell, asked whether to clear the canvas
+    fn asking_to_clear() -> TemplateApp {
+        let mut app = TemplateApp::default();
+        app.ref_matrix[0].set(0, 0, Some((1, 1)));
+        app.ask("Clear", "", Deferred::ClearCanvas);
+        app
+    }
+
+    #[test]
+    fn proceed_runs_the_action() {
+        let mut app = asking_to_clear();
+        app.resolve_confirm(&egui::Context::default(), Choice::Proceed);
+        assert!(app.confirm.is_none());
+        assert_eq!(app.ref_matrix[0].get(0, 0), None);
+    }
+
+    #[test]
+    fn cancel_keeps_everything() {
+        let mut app = asking_to_clear();
+        app.resolve_confirm(&egui::Context::default(), Choice::Cancel);
+        assert!(app.confirm.is_none());
+        assert_eq!(app.ref_matrix[0].get(0, 0), Some((1, 1)));
+    }
+
+    #[test]
+    fn save_writes_the_project_then_runs_the_action() {
+        let path = std::env::temp_dir().join(format!("confirm_save_{}.pxref", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut app = asking_to_clear();
+        app.file_path = Some("reference.png".to_string());
+        app.project_path = Some(path.clone());
+        app.resolve_confirm(&egui::Context::default(), Choice::Save);
+        let saved = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(saved.expect("project written").contains("reference.png"));
+        assert!(app.confirm.is_none());
+        assert_eq!(app.ref_matrix[0].get(0, 0), None);
+    }
+
+    #[test]
+    fn failed_save_cancels_the_action() {
+        // Projects can't be saved without a reference
+        let mut app = asking_to_clear();
+        app.project_path = Some("unused.pxref".to_string());
+        app.resolve_confirm(&egui::Context::default(), Choice::Save);
+        assert!(app.confirm.is_none());
+        assert_eq!(app.ref_matrix[0].get(0, 0), Some((1, 1)));
+        assert_eq!(
+            app.toasts.list.last().map(|toast| toast.level),
+            Some(Level::Error)
+        );
+    }
+}
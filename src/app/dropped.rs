@@ -0,0 +1,178 @@
+use super::{
+    decode_png, file_name, image_to_matrix, read_png, ColorMatrix, Deferred, Done, Frame, Job,
+    Level, ReferenceImage, TemplateApp,
+};
+use egui::{Color32, DroppedFile, FontId};
+use image::RgbaImage;
+
+/// Turns an image into a frame by pointing each opaque pixel at the reference pixel with the
+/// same color, or the closest one if there's no exact match. Pixels outside `size` are cropped.
+/// Returns the frame and how many pixels had no exact match.
+pub fn auto_map(
+    image: &ColorMatrix,
+    reference: &ColorMatrix,
+    size: (usize, usize),
+) -> (Frame, usize) {
+    let palette: Vec<((usize, usize), [u8; 4])> = reference
+        .iter()
+        .enumerate()
+        .flat_map(|(x, col)| {
+            col.iter().enumerate().filter_map(move |(y, color)| {
+                color.map(|color| ((x, y), color.to_srgba_unmultiplied()))
+            })
+        })
+        .collect();
+    let distance = |a: [u8; 4], b: [u8; 4]| -> u32 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (*a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    };
+
+    let mut frame = Frame::new(size);
+    let mut inexact = 0;
+    for (x, col) in image.iter().enumerate().take(size.0) {
+        for (y, color) in col.iter().enumerate().take(size.1) {
+            let Some(color) = color.map(|color| color.to_srgba_unmultiplied()) else {
+                continue;
+            };
+            let closest = palette
+                .iter()
+                .min_by_key(|(_, ref_color)| distance(color, *ref_color));
+            if let Some((pos, ref_color)) = closest {
+                if *ref_color != color {
+                    inexact += 1;
+                }
+                frame.set(x, y, Some(*pos));
+            }
+        }
+    }
+    (frame, inexact)
+}
+
+/// Decodes a dropped image from disk (native) or from its bytes (web)
+fn dropped_image(file: &DroppedFile) -> Result<RgbaImage, String> {
+    let result = match (&file.path, &file.bytes) {
+        (Some(path), _) => read_png(&path.to_string_lossy()),
+        (None, Some(bytes)) => decode_png(bytes),
+        (None, None) => return Err("The file couldn't be read".to_string()),
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// Whether the file name has an extension of an image format we can decode
+fn is_image(name: &str) -> bool {
+    image::ImageFormat::from_path(name).is_ok_and(|format| format.reading_enabled())
+}
+
+fn dropped_name(file: &DroppedFile) -> String {
+    match &file.path {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => file.name.clone(),
+    }
+}
+
+impl TemplateApp {
+    /// A .pxref opens the project, an image becomes the reference, or a new auto-mapped frame
+    /// when dropped on the canvas
+    pub(super) fn update_dropped_files(&mut self, ctx: &egui::Context) {
+        let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+        if hovering {
+            self.paint_drop_hint(ctx);
+        }
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        let on_canvas = ctx
+            .input(|i| i.pointer.latest_pos())
+            .is_some_and(|pos| self.layout.canvas.rect.contains(pos));
+        for file in dropped {
+            let name = dropped_name(&file);
+            if name.ends_with(".pxref") {
+                let action = match (&file.path, &file.bytes) {
+                    (Some(path), _) => Deferred::OpenProject(path.to_string_lossy().to_string()),
+                    (None, Some(bytes)) => Deferred::OpenProjectJson {
+                        json: String::from_utf8_lossy(bytes).to_string(),
+                        name,
+                    },
+                    (None, None) => {
+                        self.notify(
+                            Level::Error,
+                            "Unable to open Ref",
+                            "The file couldn't be read",
+                        );
+                        continue;
+                    }
+                };
+                self.after_discard(ctx, action);
+                continue;
+            }
+            if !is_image(&name) {
+                let message = format!("{} isn't an image or a .pxref project", file_name(&name));
+                self.notify(Level::Error, "Unsupported file", &message);
+                continue;
+            }
+            let title = format!("Opening {}", file_name(&name));
+            let job = if on_canvas {
+                let (reference, size) = (self.color_matrix.clone(), self.frame_size());
+                Job::new(ctx, &title, move |_| {
+                    let (frame, inexact) =
+                        auto_map(&image_to_matrix(&dropped_image(&file)?), &reference, size);
+                    Ok(Done::Frame { frame, inexact })
+                })
+            } else {
+                Job::new(ctx, &title, move |_| {
+                    let reference = ReferenceImage::new(dropped_image(&file)?);
+                    let path = file.path.is_some().then(|| name.clone());
+                    Ok(Done::Reference {
+                        name,
+                        path,
+                        reference,
+                    })
+                })
+            };
+            self.start_job(job);
+        }
+    }
+
+    fn paint_drop_hint(&self, ctx: &egui::Context) {
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("drop_hint"),
+        ));
+        let screen = ctx.screen_rect();
+        painter.rect_filled(screen, 0.0, Color32::from_black_alpha(160));
+        painter.rect_stroke(
+            self.layout.canvas.rect,
+            0.0,
+            egui::Stroke::new(2.0, Color32::YELLOW),
+        );
+        painter.text(
+            self.layout.canvas.rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop here to map\ninto a new frame",
+            FontId::proportional(16.0),
+            Color32::YELLOW,
+        );
+        painter.text(
+            screen.center_bottom() - egui::vec2(0., 40.),
+            egui::Align2::CENTER_CENTER,
+            "Drop an image to use it as the reference, or a .pxref to open it",
+            FontId::proportional(18.0),
+            Color32::WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_image_extensions_are_accepted() {
+        assert!(is_image("sprites/hero.png"));
+        assert!(is_image("HERO.PNG"));
+        assert!(is_image("photo.jpg"));
+        assert!(!is_image("notes.txt"));
+        assert!(!is_image("hero.pxref"));
+        assert!(!is_image("no_extension"));
+    }
+}
@@ -0,0 +1,243 @@
+use super::RefMatrix;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A cell's ref packed in 32 bits, x in the high half and y in the low half, `EMPTY` when unset.
+/// Coordinates past `MAX` are clamped to it, which is still out of bounds for any reference.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Cell(u32);
+
+impl Cell {
+    const EMPTY: Cell = Cell(u32::MAX);
+    const MAX: usize = u16::MAX as usize - 1;
+
+    fn new(value: Option<(usize, usize)>) -> Self {
+        match value {
+            Some((x, y)) => Cell(((x.min(Self::MAX) as u32) << 16) | y.min(Self::MAX) as u32),
+            None => Self::EMPTY,
+        }
+    }
+
+    fn get(self) -> Option<(usize, usize)> {
+        (self != Self::EMPTY).then_some(((self.0 >> 16) as usize, (self.0 & 0xffff) as usize))
+    }
+}
+
+/// Source of `Frame::version`, never handing out the same one twice
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// One animation frame: a grid of refs stored as a single row-major buffer. The buffer is shared
+/// between clones until one of them is edited, so undo snapshots only copy the frames that changed.
+/// Saved in projects as the nested `[x][y]` matrix they always used.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(from = "RefMatrix", into = "RefMatrix")]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Arc<Vec<Cell>>,
+    version: u64,
+}
+
+impl Frame {
+    /// Empty frame of `size` cells
+    pub fn new(size: (usize, usize)) -> Self {
+        Self {
+            width: size.0,
+            height: size.1,
+            cells: Arc::new(vec![Cell::EMPTY; size.0 * size.1]),
+            version: next_version(),
+        }
+    }
+
+    /// Changes on every edit and is kept by clones, so it identifies the cells without comparing
+    /// them. Unlike the buffer's address it can't come back after an edit in place or a free.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Ref of cell `(x, y)`, which must be inside the frame
+    pub fn get(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        assert!(
+            x < self.width && y < self.height,
+            "cell ({}, {}) outside a {}x{} frame",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        self.cells[y * self.width + x].get()
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: Option<(usize, usize)>) {
+        assert!(
+            x < self.width && y < self.height,
+            "cell ({}, {}) outside a {}x{} frame",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let width = self.width;
+        Arc::make_mut(&mut self.cells)[y * width + x] = Cell::new(value);
+        self.version = next_version();
+    }
+
+    /// Every cell with its ref, row by row
+    pub fn cells(&self) -> impl Iterator<Item = ((usize, usize), Option<(usize, usize)>)> + '_ {
+        let width = self.width.max(1);
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(k, cell)| ((k % width, k / width), cell.get()))
+    }
+
+    /// Refs of the cells that have one
+    pub fn refs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.iter().filter_map(|cell| cell.get())
+    }
+
+    /// Rewrites every ref through `map`, returns how many cells changed. The buffer is only
+    /// copied if something does change.
+    pub fn map_refs(&mut self, map: impl Fn((usize, usize)) -> Option<(usize, usize)>) -> usize {
+        let changes: Vec<(usize, Cell)> = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter_map(|(k, cell)| {
+                let pos = cell.get()?;
+                map(pos)
+                    .filter(|new_pos| *new_pos != pos)
+                    .map(|new_pos| (k, Cell::new(Some(new_pos))))
+            })
+            .collect();
+        if !changes.is_empty() {
+            let cells = Arc::make_mut(&mut self.cells);
+            for (k, cell) in &changes {
+                cells[*k] = *cell;
+            }
+            self.version = next_version();
+        }
+        changes.len()
+    }
+
+    /// Nested `[x][y]` copy, for the transforms shared with other matrices
+    pub fn to_matrix(&self) -> RefMatrix {
+        (0..self.width)
+            .map(|x| (0..self.height).map(|y| self.get(x, y)).collect())
+            .collect()
+    }
+}
+
+impl PartialEq for Frame {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+            || self.size() == other.size()
+                && (Arc::ptr_eq(&self.cells, &other.cells) || self.cells == other.cells)
+    }
+}
+
+impl From<RefMatrix> for Frame {
+    fn from(matrix: RefMatrix) -> Self {
+        let (width, height) = (matrix.len(), matrix.first().map_or(0, |col| col.len()));
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| Cell::new(matrix[x].get(y).copied().flatten()))
+            .collect();
+        Self {
+            width,
+            height,
+            cells: Arc::new(cells),
+            version: next_version(),
+        }
+    }
+}
+
+impl From<Frame> for RefMatrix {
+    fn from(frame: Frame) -> Self {
+        frame.to_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_pack_both_coordinates() {
+        assert_eq!(Cell::new(None), Cell::EMPTY);
+        assert_eq!(Cell::new(None).get(), None);
+        assert_eq!(Cell::new(Some((0, 0))).get(), Some((0, 0)));
+        assert_eq!(Cell::new(Some((3, 70))).get(), Some((3, 70)));
+        assert_eq!(
+            Cell::new(Some((Cell::MAX, Cell::MAX))).get(),
+            Some((Cell::MAX, Cell::MAX))
+        );
+    }
+
+    #[test]
+    fn huge_refs_clamp_without_becoming_empty() {
+        let cell = Cell::new(Some((usize::MAX, 1 << 20)));
+        assert_ne!(cell, Cell::EMPTY);
+        assert_eq!(cell.get(), Some((Cell::MAX, Cell::MAX)));
+    }
+
+    #[test]
+    fn round_trips_the_nested_project_format() {
+        // Two columns of three cells, indexed [x][y]
+        let json = "[[null,[1,2],null],[[3,4],null,[5,6]]]";
+        let frame: Frame = serde_json::from_str(json).unwrap();
+        assert_eq!(frame.size(), (2, 3));
+        assert_eq!(frame.get(0, 1), Some((1, 2)));
+        assert_eq!(frame.get(1, 0), Some((3, 4)));
+        assert_eq!(frame.get(1, 2), Some((5, 6)));
+        assert_eq!(frame.get(0, 0), None);
+        assert_eq!(serde_json::to_string(&frame).unwrap(), json);
+    }
+
+    #[test]
+    fn jagged_matrices_fill_missing_cells_with_none() {
+        let frame = Frame::from(vec![vec![Some((0, 0)), Some((1, 1))], vec![Some((2, 2))]]);
+        assert_eq!(frame.size(), (2, 2));
+        assert_eq!(frame.get(1, 0), Some((2, 2)));
+        assert_eq!(frame.get(1, 1), None);
+    }
+
+    #[test]
+    fn map_refs_copies_the_buffer_only_on_change() {
+        let mut original = Frame::new((2, 2));
+        original.set(0, 0, Some((1, 1)));
+        original.set(1, 1, Some((2, 2)));
+        let mut copy = original.clone();
+        assert_eq!(copy.map_refs(Some), 0);
+        assert!(Arc::ptr_eq(&copy.cells, &original.cells));
+        assert_eq!(
+            copy.map_refs(|pos| (pos == (1, 1)).then_some((9, 9)).or(Some(pos))),
+            1
+        );
+        assert!(!Arc::ptr_eq(&copy.cells, &original.cells));
+        assert_eq!(copy.get(0, 0), Some((9, 9)));
+        assert_eq!(copy.get(1, 1), Some((2, 2)));
+        assert_eq!(original.get(0, 0), Some((1, 1)));
+    }
+
+    #[test]
+    fn frames_compare_by_size_and_content() {
+        let mut a = Frame::new((2, 2));
+        a.set(1, 0, Some((4, 4)));
+        let mut b = Frame::new((2, 2));
+        b.set(1, 0, Some((4, 4)));
+        assert!(a == b);
+        assert!(a == a.clone());
+        b.set(0, 1, Some((4, 4)));
+        assert!(a != b);
+        assert!(Frame::new((2, 3)) != Frame::new((3, 2)));
+    }
+}
@@ -0,0 +1,70 @@
+use super::TemplateApp;
+use egui::{Color32, Painter, Stroke};
+
+const HIGHLIGHT: Color32 = Color32::from_rgb(0, 200, 255);
+
+impl TemplateApp {
+    /// Reference pixel under the pointer, either hovered directly on the right panel or used by
+    /// the hovered canvas cell
+    pub(super) fn hovered_ref(&self, ctx: &egui::Context) -> Option<(usize, usize)> {
+        let pointer = ctx.input(|i| i.pointer.latest_pos())?;
+        if let Some(cell) = self
+            .layout
+            .reference_cell_at(ctx, pointer, self.reference_size())
+        {
+            return Some(cell);
+        }
+        let (x, y) = self
+            .layout
+            .canvas_cell_at(ctx, pointer, self.frame_size())?;
+        self.ref_matrix[self.current_frame].get(x, y)
+    }
+
+    /// Outlines the hovered ref on the reference panel and every canvas cell using it
+    pub(super) fn paint_hover_highlights(
+        &self,
+        hovered: (usize, usize),
+        canvas_painter: &Painter,
+        reference_painter: &Painter,
+    ) {
+        let stroke = Stroke::new(2.0, HIGHLIGHT);
+        reference_painter.rect_stroke(
+            self.layout.reference.cell_rect(hovered.0, hovered.1),
+            0.0,
+            stroke,
+        );
+        for ((x, y), cell) in self.ref_matrix[self.current_frame].cells() {
+            if cell == Some(hovered) {
+                canvas_painter.rect_stroke(
+                    self.layout.canvas.cell_rect(x, y).shrink(1.0),
+                    0.0,
+                    stroke,
+                );
+            }
+        }
+    }
+
+    /// Marks frame thumbnail `j` if it uses the hovered ref, with the number of cells using it
+    pub(super) fn paint_frame_highlight(
+        &self,
+        hovered: (usize, usize),
+        j: usize,
+        rect: egui::Rect,
+        painter: &Painter,
+    ) {
+        let uses = self.ref_matrix[j]
+            .refs()
+            .filter(|pos| *pos == hovered)
+            .count();
+        if uses > 0 {
+            painter.rect_stroke(rect.shrink(1.0), 0.0, Stroke::new(2.0, HIGHLIGHT));
+            painter.text(
+                rect.right_bottom(),
+                egui::Align2::RIGHT_BOTTOM,
+                uses.to_string(),
+                egui::FontId::proportional(10.0),
+                HIGHLIGHT,
+            );
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use super::{Frame, TemplateApp};
+
+/// Snapshots of every frame, taken whenever an edit is finished. Frames share their cells with
+/// the snapshots until edited, so a step only costs the frames that changed.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Frame>>,
+    redo: Vec<Vec<Frame>>,
+    current: Vec<Frame>,
+}
+
+impl History {
+    /// Oldest steps are dropped past this
+    const LIMIT: usize = 100;
+
+    pub fn new(frames: &[Frame]) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: frames.to_vec(),
+        }
+    }
+
+    /// Records `frames` as a new step if they changed since the last one
+    pub fn record(&mut self, frames: &[Frame]) {
+        if frames == self.current.as_slice() {
+            return;
+        }
+        self.undo
+            .push(std::mem::replace(&mut self.current, frames.to_vec()));
+        if self.undo.len() > Self::LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Restores the previous step into `frames`, false if there's none
+    pub fn undo(&mut self, frames: &mut Vec<Frame>) -> bool {
+        self.record(frames);
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        self.redo
+            .push(std::mem::replace(&mut self.current, previous.clone()));
+        *frames = previous;
+        true
+    }
+
+    pub fn redo(&mut self, frames: &mut Vec<Frame>) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.undo
+            .push(std::mem::replace(&mut self.current, next.clone()));
+        *frames = next;
+        true
+    }
+}
+
+impl TemplateApp {
+    /// Takes a history step once the pointer is released, so a whole stroke undoes at once
+    pub(super) fn record_history(&mut self, ctx: &egui::Context) {
+        if !ctx.input(|i| i.pointer.any_down()) && self.floating.is_none() {
+            self.history.record(&self.ref_matrix);
+        }
+    }
+
+    pub(super) fn undo(&mut self) {
+        self.floating = None;
+        if self.history.undo(&mut self.ref_matrix) {
+            self.current_frame = self.current_frame.min(self.ref_matrix.len() - 1);
+            self.refresh_problems();
+        }
+    }
+
+    pub(super) fn redo(&mut self) {
+        self.floating = None;
+        if self.history.redo(&mut self.ref_matrix) {
+            self.current_frame = self.current_frame.min(self.ref_matrix.len() - 1);
+            self.refresh_problems();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with(value: usize) -> Vec<Frame> {
+        let mut frame = Frame::new((2, 2));
+        frame.set(0, 0, Some((value, value)));
+        vec![frame]
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_steps() {
+        let mut history = History::new(&frame_with(0));
+        history.record(&frame_with(1));
+        history.record(&frame_with(2));
+        let mut frames = frame_with(2);
+        assert!(history.undo(&mut frames));
+        assert!(frames == frame_with(1));
+        assert!(history.undo(&mut frames));
+        assert!(frames == frame_with(0));
+        assert!(!history.undo(&mut frames));
+        assert!(history.redo(&mut frames));
+        assert!(history.redo(&mut frames));
+        assert!(frames == frame_with(2));
+        assert!(!history.redo(&mut frames));
+    }
+
+    #[test]
+    fn unchanged_frames_are_not_a_step() {
+        let mut history = History::new(&frame_with(0));
+        history.record(&frame_with(0));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_keeps_unrecorded_edits_redoable() {
+        let mut history = History::new(&frame_with(0));
+        let mut frames = frame_with(1);
+        assert!(history.undo(&mut frames));
+        assert!(frames == frame_with(0));
+        assert!(history.redo(&mut frames));
+        assert!(frames == frame_with(1));
+    }
+
+    #[test]
+    fn new_edits_drop_the_redo_steps() {
+        let mut history = History::new(&frame_with(0));
+        history.record(&frame_with(1));
+        let mut frames = frame_with(1);
+        history.undo(&mut frames);
+        history.record(&frame_with(5));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn oldest_steps_are_dropped_past_the_limit() {
+        let mut history = History::new(&frame_with(0));
+        for value in 1..=History::LIMIT + 10 {
+            history.record(&frame_with(value));
+        }
+        let mut frames = frame_with(History::LIMIT + 10);
+        while history.undo(&mut frames) {}
+        assert!(frames == frame_with(10));
+    }
+}
@@ -0,0 +1,230 @@
+use super::{push_recent, ColorMatrix, Frame, Level, PxRefFile, ReferenceImage, TemplateApp};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+/// Shared between a job and the UI: how far along it is, and whether the user gave up on it
+#[derive(Default)]
+pub struct Progress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn set(&self, done: usize, total: usize) {
+        self.done.store(done, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Jobs check this between steps and stop early, their result is thrown away anyway
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `None` while the job hasn't reported any steps
+    fn fraction(&self) -> Option<f32> {
+        let total = self.total.load(Ordering::Relaxed);
+        (total > 0).then(|| self.done.load(Ordering::Relaxed) as f32 / total as f32)
+    }
+}
+
+/// What a finished job hands back, applied to the app on the UI thread
+pub enum Done {
+    Reference {
+        name: String,
+        path: Option<String>,
+        reference: ReferenceImage,
+    },
+    OldReference(ColorMatrix),
+    /// Image auto-mapped into a new frame, with how many pixels had no exact color
+    Frame {
+        frame: Frame,
+        inexact: usize,
+    },
+    /// `snapshot` if restored from recovery, so it isn't considered saved
+    Project {
+        name: Option<String>,
+        path: Option<String>,
+        data: PxRefFile,
+        reference: ReferenceImage,
+        snapshot: bool,
+    },
+    Exported {
+        files: usize,
+    },
+}
+
+/// Work running off the UI thread
+pub struct Job {
+    pub title: String,
+    /// Recent file to forget if the job fails
+    recent: Option<String>,
+    progress: Arc<Progress>,
+    receiver: mpsc::Receiver<Result<Done, String>>,
+}
+
+impl Job {
+    pub fn new(
+        ctx: &egui::Context,
+        title: &str,
+        work: impl FnOnce(&Progress) -> Result<Done, String> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let progress = Arc::new(Progress::default());
+        let run = {
+            let progress = progress.clone();
+            let ctx = ctx.clone();
+            move || {
+                // The receiver is gone if the job was cancelled
+                let _ = sender.send(work(&progress));
+                ctx.request_repaint();
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(run);
+        // Browsers only get threads with a special build, so the work runs right away there
+        #[cfg(target_arch = "wasm32")]
+        run();
+        Self {
+            title: title.to_string(),
+            recent: None,
+            progress,
+            receiver,
+        }
+    }
+
+    pub fn forget_on_failure(mut self, recent: Option<String>) -> Self {
+        self.recent = recent;
+        self
+    }
+}
+
+impl TemplateApp {
+    pub(super) fn start_job(&mut self, job: Job) {
+        self.jobs.push(job);
+    }
+
+    /// Applies the results of the jobs that finished since the last frame
+    pub(super) fn update_jobs(&mut self) {
+        let mut k = 0;
+        while k < self.jobs.len() {
+            let result = match self.jobs[k].receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => {
+                    k += 1;
+                    continue;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    Err("The task stopped unexpectedly".to_string())
+                }
+            };
+            let job = self.jobs.remove(k);
+            match result {
+                Ok(done) => self.finish_job(done),
+                Err(e) => {
+                    if let Some(path) = &job.recent {
+                        self.recent_files.retain(|p| p != path);
+                    }
+                    self.notify(Level::Error, &format!("{} failed", job.title), &e);
+                }
+            }
+        }
+    }
+
+    fn finish_job(&mut self, done: Done) {
+        match done {
+            Done::Reference {
+                name,
+                path,
+                reference,
+            } => {
+                self.set_reference(&name, reference);
+                if let Some(path) = path {
+                    push_recent(&mut self.recent_files, &path);
+                }
+            }
+            Done::OldReference(old) => self.remap_from_old(&old),
+            Done::Frame { frame, inexact } => {
+                self.ref_matrix.push(frame);
+                self.current_frame = self.ref_matrix.len() - 1;
+                if inexact > 0 {
+                    let message = format!(
+                        "{} pixels had no exact color in the reference and use the closest one",
+                        inexact
+                    );
+                    self.notify(Level::Info, "Auto-mapped Frame", &message);
+                }
+            }
+            Done::Project {
+                name,
+                path,
+                data,
+                reference,
+                snapshot,
+            } => {
+                // Snapshots only embed the reference when its colors were edited in the app
+                let edited_reference = snapshot && data.ref_png_data.is_some();
+                self.apply_project(data, reference);
+                self.project_path = name;
+                if snapshot {
+                    self.snapshots.clear();
+                    self.reference_modified = edited_reference;
+                } else {
+                    self.mark_saved();
+                }
+                if let Some(path) = path {
+                    push_recent(&mut self.recent_files, &path);
+                }
+            }
+            Done::Exported { files } => {
+                let message = if files == 1 {
+                    "1 image written".to_string()
+                } else {
+                    format!("{} images written", files)
+                };
+                self.notify(Level::Info, "Export Finished", &message);
+            }
+        }
+    }
+
+    /// Running jobs with their progress, in the bottom left corner
+    pub(super) fn jobs_window(&mut self, ctx: &egui::Context) {
+        if self.jobs.is_empty() {
+            return;
+        }
+        let mut cancel = None;
+        egui::Area::new(egui::Id::new("jobs"))
+            .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(12., -12.))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (k, job) in self.jobs.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            match job.progress.fraction() {
+                                Some(fraction) => {
+                                    ui.add(
+                                        egui::ProgressBar::new(fraction)
+                                            .desired_width(120.)
+                                            .show_percentage(),
+                                    );
+                                }
+                                None => {
+                                    ui.spinner();
+                                }
+                            }
+                            ui.label(&job.title);
+                            if ui.small_button("Cancel").clicked() {
+                                cancel = Some(k);
+                            }
+                        });
+                    }
+                });
+            });
+        if let Some(k) = cancel {
+            let job = self.jobs.remove(k);
+            job.progress.cancelled.store(true, Ordering::Relaxed);
+        }
+        // Progress is reported from other threads, keep it moving on screen
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    }
+}
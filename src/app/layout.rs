@@ -0,0 +1,314 @@
+use super::{PanelView, TemplateApp};
+use egui::{vec2, LayerId, Pos2, Rect, Vec2};
+
+const MARGIN: f32 = 16.;
+/// Side of the frame thumbnails and the play button
+pub const FRAME_SIZE: f32 = 32.;
+
+/// A panel's screen rect together with its zoom and pan, converting between screen positions
+/// and cells
+#[derive(Clone, Copy)]
+pub struct Panel {
+    pub rect: Rect,
+    pub view: PanelView,
+}
+
+impl Panel {
+    pub fn cell_size(&self) -> f32 {
+        self.view.cell_size()
+    }
+
+    /// Top-left corner of cell `(x, y)`, cells outside the grid are allowed (for outlines)
+    pub fn cell_pos(&self, x: i32, y: i32) -> Pos2 {
+        self.rect.min + self.view.pan + vec2(x as f32, y as f32) * self.cell_size()
+    }
+
+    pub fn cell_rect(&self, x: usize, y: usize) -> Rect {
+        Rect::from_min_size(
+            self.cell_pos(x as i32, y as i32),
+            Vec2::splat(self.cell_size()),
+        )
+    }
+
+    /// Screen rect covered by a whole `size` grid
+    pub fn grid_rect(&self, size: (usize, usize)) -> Rect {
+        Rect::from_min_size(
+            self.cell_pos(0, 0),
+            vec2(size.0 as f32, size.1 as f32) * self.cell_size(),
+        )
+    }
+
+    /// Cell of a `size.0` x `size.1` grid under the screen position `pos`
+    pub fn cell_at(&self, pos: Pos2, size: (usize, usize)) -> Option<(usize, usize)> {
+        if !self.rect.contains(pos) {
+            return None;
+        }
+        let offset = (pos - self.cell_pos(0, 0)) / self.cell_size();
+        if offset.x < 0. || offset.y < 0. {
+            return None;
+        }
+        let cell = (offset.x as usize, offset.y as usize);
+        (cell.0 < size.0 && cell.1 < size.1).then_some(cell)
+    }
+}
+
+/// Horizontal scroll of the frame strip, kept by the app since the layout is rebuilt every frame
+#[derive(Clone, Copy, Default)]
+pub struct StripScroll {
+    pub offset: f32,
+    /// Current frame when it was last scrolled into view, to follow it when it changes
+    followed: usize,
+}
+
+impl StripScroll {
+    /// Scrolls by the mouse wheel `delta`, and brings the current frame into view when it
+    /// changes, without going past either end of the thumbnails and "+" button
+    pub fn update(&mut self, delta: f32, strip: Rect, frames_len: usize, current: usize) {
+        if current != self.followed {
+            let left = (FRAME_SIZE + MARGIN) * current as f32;
+            self.offset = self.offset.max(left + FRAME_SIZE - strip.width()).min(left);
+            self.followed = current;
+        }
+        let content = (FRAME_SIZE + MARGIN) * (frames_len + 1) as f32 - MARGIN;
+        self.offset = (self.offset - delta).min(content - strip.width()).max(0.);
+    }
+}
+
+/// Where everything in the central panel goes, recomputed every frame from the space egui gives
+/// us so hit testing always matches what's drawn
+pub struct Layout {
+    /// Layer the panels are painted on, positions covered by windows on top don't hit them
+    pub layer: LayerId,
+    pub canvas: Panel,
+    pub reference: Panel,
+    /// Row of frame thumbnails, ending with the "+" button
+    pub frames: Rect,
+    /// How far the thumbnails are scrolled left within `frames`
+    frames_scroll: f32,
+    pub play_button: Rect,
+}
+
+impl Layout {
+    /// Canvas and reference rects: side by side above the frame strip
+    fn panel_rects(area: Rect) -> (Rect, Rect) {
+        let top = Rect::from_min_max(
+            area.min,
+            Pos2::new(
+                area.max.x,
+                (area.max.y - FRAME_SIZE - MARGIN).max(area.min.y),
+            ),
+        );
+        let width = ((top.width() - MARGIN) / 2.).max(0.);
+        let canvas = Rect::from_min_size(top.min, vec2(width, top.height()));
+        let reference = Rect::from_min_size(
+            top.min + vec2(width + MARGIN, 0.),
+            vec2(width, top.height()),
+        );
+        (canvas, reference)
+    }
+
+    pub fn new(ui: &egui::Ui, canvas_view: PanelView, reference_view: PanelView) -> Self {
+        let area = ui.available_rect_before_wrap();
+        let (canvas, reference) = Self::panel_rects(area);
+        let strip = Rect::from_min_max(Pos2::new(area.min.x, area.max.y - FRAME_SIZE), area.max);
+        Self {
+            layer: ui.layer_id(),
+            canvas: Panel {
+                rect: canvas,
+                view: canvas_view,
+            },
+            reference: Panel {
+                rect: reference,
+                view: reference_view,
+            },
+            frames: Rect::from_min_max(
+                strip.min,
+                Pos2::new(strip.max.x - FRAME_SIZE - MARGIN, strip.max.y),
+            ),
+            frames_scroll: 0.,
+            play_button: Rect::from_min_size(
+                Pos2::new(strip.max.x - FRAME_SIZE, strip.min.y),
+                Vec2::splat(FRAME_SIZE),
+            ),
+        }
+    }
+
+    /// False if a window or popup is drawn over `pos`
+    pub fn is_visible(&self, ctx: &egui::Context, pos: Pos2) -> bool {
+        ctx.layer_id_at(pos)
+            .map_or(true, |layer| layer == self.layer)
+    }
+
+    pub fn canvas_cell_at(
+        &self,
+        ctx: &egui::Context,
+        pos: Pos2,
+        size: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        self.canvas
+            .cell_at(pos, size)
+            .filter(|_| self.is_visible(ctx, pos))
+    }
+
+    pub fn reference_cell_at(
+        &self,
+        ctx: &egui::Context,
+        pos: Pos2,
+        size: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        self.reference
+            .cell_at(pos, size)
+            .filter(|_| self.is_visible(ctx, pos))
+    }
+
+    /// Thumbnail of frame `j`, `j == frames_len` is the "+" button. It may be scrolled partly or
+    /// entirely out of `frames`.
+    pub fn frame_rect(&self, j: usize) -> Rect {
+        let x = (FRAME_SIZE + MARGIN) * j as f32 - self.frames_scroll;
+        Rect::from_min_size(self.frames.min + vec2(x, 0.), Vec2::splat(FRAME_SIZE))
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            layer: LayerId::background(),
+            canvas: Panel {
+                rect: Rect::NOTHING,
+                view: PanelView::default(),
+            },
+            reference: Panel {
+                rect: Rect::NOTHING,
+                view: PanelView::default(),
+            },
+            frames: Rect::NOTHING,
+            frames_scroll: 0.,
+            play_button: Rect::NOTHING,
+        }
+    }
+}
+
+impl TemplateApp {
+    /// Lays out the central panel and applies this frame's zoom and pan input to both panels
+    pub(super) fn update_layout(&mut self, ui: &egui::Ui) {
+        let ctx = ui.ctx();
+        let mut layout = Layout::new(ui, self.canvas_view, self.reference_view);
+        let pointer = ctx.input(|i| i.pointer.latest_pos());
+        let hovered = |rect: Rect| {
+            pointer.is_some_and(|pos| rect.contains(pos) && layout.is_visible(ctx, pos))
+        };
+        self.canvas_view
+            .handle_input(ctx, layout.canvas.rect, hovered(layout.canvas.rect));
+        self.reference_view.handle_input(
+            ctx,
+            layout.reference.rect,
+            hovered(layout.reference.rect),
+        );
+        let scroll = if hovered(layout.frames) {
+            ctx.input(|i| i.smooth_scroll_delta.x + i.smooth_scroll_delta.y)
+        } else {
+            0.
+        };
+        self.strip_scroll.update(
+            scroll,
+            layout.frames,
+            self.ref_matrix.len(),
+            self.current_frame,
+        );
+        layout.frames_scroll = self.strip_scroll.offset;
+        layout.canvas.view = self.canvas_view;
+        layout.reference.view = self.reference_view;
+        self.layout = layout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    /// 200x100 panel at (100, 50), cells 16 points wide at zoom 1
+    fn panel(view: PanelView) -> Panel {
+        Panel {
+            rect: Rect::from_min_size(pos2(100., 50.), vec2(200., 100.)),
+            view,
+        }
+    }
+
+    #[test]
+    fn cell_at_edges_and_corners() {
+        let panel = panel(PanelView::default());
+        assert_eq!(panel.cell_at(pos2(100., 50.), (4, 2)), Some((0, 0)));
+        assert_eq!(panel.cell_at(pos2(115.9, 65.9), (4, 2)), Some((0, 0)));
+        assert_eq!(panel.cell_at(pos2(116., 50.), (4, 2)), Some((1, 0)));
+        assert_eq!(panel.cell_at(pos2(100., 66.), (4, 2)), Some((0, 1)));
+        assert_eq!(panel.cell_at(pos2(163.9, 81.9), (4, 2)), Some((3, 1)));
+    }
+
+    #[test]
+    fn cell_at_outside_the_grid_or_panel() {
+        let panel = panel(PanelView::default());
+        // Past the last column and row of a non-square grid, still inside the panel
+        assert_eq!(panel.cell_at(pos2(164., 60.), (4, 2)), None);
+        assert_eq!(panel.cell_at(pos2(110., 82.), (4, 2)), None);
+        assert_eq!(panel.cell_at(pos2(110., 82.), (2, 4)), Some((0, 2)));
+        // Outside the panel
+        assert_eq!(panel.cell_at(pos2(99., 60.), (4, 2)), None);
+        assert_eq!(panel.cell_at(pos2(110., 49.), (4, 2)), None);
+    }
+
+    #[test]
+    fn cell_at_follows_zoom_and_pan() {
+        let mut view = PanelView::default();
+        view.zoom = 2.;
+        view.pan = vec2(10., -20.);
+        let panel = panel(view);
+        // Cells are 32 points, the grid starts at (110, 30)
+        assert_eq!(panel.cell_at(pos2(109., 60.), (4, 4)), None);
+        assert_eq!(panel.cell_at(pos2(110., 62.), (4, 4)), Some((0, 1)));
+        assert_eq!(panel.cell_at(pos2(143., 95.), (4, 4)), Some((1, 2)));
+        // Cell (0, 0) is panned above the panel, where it can't be clicked
+        assert_eq!(panel.cell_at(pos2(120., 40.), (4, 4)), None);
+        assert_eq!(panel.cell_rect(1, 2).min, pos2(142., 94.));
+    }
+
+    #[test]
+    fn frame_rects_are_scrolled() {
+        let mut layout = Layout {
+            frames: Rect::from_min_size(pos2(0., 0.), vec2(100., FRAME_SIZE)),
+            ..Layout::default()
+        };
+        assert_eq!(
+            layout.frame_rect(2).min,
+            pos2(2. * (FRAME_SIZE + MARGIN), 0.)
+        );
+        layout.frames_scroll = 24.;
+        assert_eq!(
+            layout.frame_rect(1).min,
+            pos2(FRAME_SIZE + MARGIN - 24., 0.)
+        );
+    }
+
+    #[test]
+    fn strip_scrolls_within_its_content_and_follows_the_current_frame() {
+        let strip = Rect::from_min_size(pos2(0., 0.), vec2(100., FRAME_SIZE));
+        let content = (FRAME_SIZE + MARGIN) * 11. - MARGIN;
+        let mut scroll = StripScroll::default();
+        scroll.update(-1000., strip, 10, 0);
+        assert_eq!(scroll.offset, content - 100.);
+        scroll.update(0., strip, 10, 5);
+        assert_eq!(scroll.offset, (FRAME_SIZE + MARGIN) * 5.);
+        scroll.update(1000., strip, 10, 5);
+        assert_eq!(scroll.offset, 0.);
+        scroll.update(0., strip, 10, 5);
+        assert_eq!(scroll.offset, 0.);
+        scroll.update(0., strip, 10, 9);
+        assert_eq!(
+            scroll.offset,
+            (FRAME_SIZE + MARGIN) * 9. + FRAME_SIZE - 100.
+        );
+        // Everything fits
+        scroll.update(-1000., strip, 1, 0);
+        assert_eq!(scroll.offset, 0.);
+    }
+}
@@ -0,0 +1,202 @@
+use super::{flip_horizontal, Frame, TemplateApp};
+
+/// Rectangle of reference pixels
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub struct RefRegion {
+    pub min: (usize, usize),
+    pub size: (usize, usize),
+}
+
+impl RefRegion {
+    /// Offset of `pos` inside the region
+    pub fn local(&self, pos: (usize, usize)) -> Option<(usize, usize)> {
+        let (dx, dy) = (
+            pos.0.checked_sub(self.min.0)?,
+            pos.1.checked_sub(self.min.1)?,
+        );
+        (dx < self.size.0 && dy < self.size.1).then_some((dx, dy))
+    }
+}
+
+/// Two reference regions holding the left and right version of the same part (e.g. the arms),
+/// swapped when a sprite changes facing
+#[derive(Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RemapPair {
+    pub name: String,
+    pub a: RefRegion,
+    pub b: RefRegion,
+    /// The parts are drawn mirrored in the reference, so offsets are flipped horizontally too
+    pub mirrored: bool,
+}
+
+impl RemapPair {
+    fn map(&self, pos: (usize, usize), from: &RefRegion, to: &RefRegion) -> Option<(usize, usize)> {
+        let (mut dx, dy) = from.local(pos)?;
+        if self.mirrored {
+            dx = from.size.0 - 1 - dx;
+        }
+        let mapped = (to.min.0 + dx, to.min.1 + dy);
+        to.local(mapped).map(|_| mapped)
+    }
+}
+
+/// Counterpart of a ref under the remap table, or the ref itself if no pair covers it
+pub fn remap_ref(pairs: &[RemapPair], pos: (usize, usize)) -> (usize, usize) {
+    pairs
+        .iter()
+        .find_map(|pair| {
+            pair.map(pos, &pair.a, &pair.b)
+                .or_else(|| pair.map(pos, &pair.b, &pair.a))
+        })
+        .unwrap_or(pos)
+}
+
+/// Flips a frame to face the other way, swapping paired refs
+pub fn mirror_frame(frame: &Frame, pairs: &[RemapPair]) -> Frame {
+    let mut mirrored = Frame::from(flip_horizontal(frame.to_matrix()));
+    mirrored.map_refs(|pos| Some(remap_ref(pairs, pos)));
+    mirrored
+}
+
+fn region_editor(ui: &mut egui::Ui, region: &mut RefRegion) {
+    ui.add(egui::DragValue::new(&mut region.min.0).prefix("x: "));
+    ui.add(egui::DragValue::new(&mut region.min.1).prefix("y: "));
+    ui.add(
+        egui::DragValue::new(&mut region.size.0)
+            .prefix("w: ")
+            .range(1..=usize::MAX),
+    );
+    ui.add(
+        egui::DragValue::new(&mut region.size.1)
+            .prefix("h: ")
+            .range(1..=usize::MAX),
+    );
+}
+
+impl TemplateApp {
+    /// Appends mirrored copies of the checked frames after the last frame
+    pub(super) fn generate_mirrored_frames(&mut self) {
+        let mirrored: Vec<Frame> = self
+            .ref_matrix
+            .iter()
+            .zip(&self.mirror_frames)
+            .filter(|(_, checked)| **checked)
+            .map(|(frame, _)| mirror_frame(frame, &self.remap_pairs))
+            .collect();
+        if !mirrored.is_empty() {
+            self.current_frame = self.ref_matrix.len();
+            self.ref_matrix.extend(mirrored);
+        }
+    }
+
+    pub(super) fn mirror_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_mirror;
+        self.mirror_frames.resize(self.ref_matrix.len(), false);
+        egui::Window::new("Mirror Frames")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Frames to mirror");
+                ui.horizontal_wrapped(|ui| {
+                    for (j, checked) in self.mirror_frames.iter_mut().enumerate() {
+                        ui.checkbox(checked, format!("{}", j + 1));
+                    }
+                });
+                ui.separator();
+                ui.label("Swapped reference regions");
+                let mut remove = None;
+                for (k, pair) in self.remap_pairs.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut pair.name);
+                        ui.checkbox(&mut pair.mirrored, "Mirrored");
+                        if ui.button("Remove").clicked() {
+                            remove = Some(k);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("A");
+                        region_editor(ui, &mut pair.a);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("B");
+                        region_editor(ui, &mut pair.b);
+                    });
+                }
+                if let Some(k) = remove {
+                    self.remap_pairs.remove(k);
+                }
+                if ui.button("Add pair").clicked() {
+                    self.remap_pairs.push(RemapPair {
+                        name: format!("Pair {}", self.remap_pairs.len() + 1),
+                        a: RefRegion {
+                            min: (0, 0),
+                            size: (1, 1),
+                        },
+                        b: RefRegion {
+                            min: (0, 0),
+                            size: (1, 1),
+                        },
+                        mirrored: false,
+                    });
+                }
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        self.mirror_frames.contains(&true),
+                        egui::Button::new("Generate"),
+                    )
+                    .clicked()
+                {
+                    self.generate_mirrored_frames();
+                }
+            });
+        self.show_mirror = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arms(mirrored: bool) -> Vec<RemapPair> {
+        vec![RemapPair {
+            name: "arms".into(),
+            a: RefRegion {
+                min: (0, 0),
+                size: (2, 2),
+            },
+            b: RefRegion {
+                min: (4, 0),
+                size: (2, 2),
+            },
+            mirrored,
+        }]
+    }
+
+    #[test]
+    fn refs_swap_between_paired_regions() {
+        let pairs = arms(false);
+        assert_eq!(remap_ref(&pairs, (0, 1)), (4, 1));
+        assert_eq!(remap_ref(&pairs, (5, 0)), (1, 0));
+        assert_eq!(remap_ref(&pairs, (3, 0)), (3, 0));
+
+        let pairs = arms(true);
+        assert_eq!(remap_ref(&pairs, (0, 1)), (5, 1));
+        assert_eq!(remap_ref(&pairs, (4, 0)), (1, 0));
+    }
+
+    #[test]
+    fn mirroring_twice_is_the_identity() {
+        let mut frame = Frame::new((3, 2));
+        frame.set(0, 0, Some((0, 0)));
+        frame.set(1, 0, Some((3, 1)));
+        frame.set(2, 1, Some((5, 1)));
+        for pairs in [arms(false), arms(true)] {
+            let once = mirror_frame(&frame, &pairs);
+            assert_eq!(once.get(2, 0), Some(remap_ref(&pairs, (0, 0))));
+            assert_eq!(once.get(1, 0), Some((3, 1)));
+            assert_eq!(once.get(0, 1), Some(remap_ref(&pairs, (5, 1))));
+            assert_eq!(mirror_frame(&once, &pairs).to_matrix(), frame.to_matrix());
+        }
+    }
+}
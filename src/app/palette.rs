@@ -0,0 +1,160 @@
+use super::{encode_png, platform, render_sheet, Done, Job, TemplateApp};
+use egui::Color32;
+
+/// Named color variant of the reference: replacement colors for some of its pixels
+#[derive(Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Palette {
+    pub name: String,
+    /// Reference pixel and its unmultiplied RGBA color in this variant
+    pub colors: Vec<((usize, usize), [u8; 4])>,
+}
+
+impl Palette {
+    /// Override for a reference pixel, `Some(None)` if the variant makes it transparent
+    pub fn get(&self, pos: (usize, usize)) -> Option<Option<Color32>> {
+        self.colors
+            .iter()
+            .find(|(p, _)| *p == pos)
+            .map(|(_, [r, g, b, a])| {
+                (*a > 0).then(|| Color32::from_rgba_unmultiplied(*r, *g, *b, *a))
+            })
+    }
+
+    pub fn set(&mut self, pos: (usize, usize), color: Option<Color32>) {
+        let rgba = color.map_or([0, 0, 0, 0], |color| color.to_srgba_unmultiplied());
+        match self.colors.iter_mut().find(|(p, _)| *p == pos) {
+            Some(entry) => entry.1 = rgba,
+            None => self.colors.push((pos, rgba)),
+        }
+    }
+
+    pub fn reset(&mut self, pos: (usize, usize)) {
+        self.colors.retain(|(p, _)| *p != pos);
+    }
+}
+
+impl TemplateApp {
+    pub(super) fn active_palette_name(&self) -> &str {
+        self.active_palette
+            .and_then(|k| self.palettes.get(k))
+            .map_or("Base", |palette| palette.name.as_str())
+    }
+
+    /// Switches the palette everything is drawn with, redrawing the textures if it changed
+    pub(super) fn set_active_palette(&mut self, active: Option<usize>) {
+        if self.active_palette != active {
+            self.active_palette = active;
+            self.colors_revision += 1;
+        }
+    }
+
+    /// Combo box switching the palette the canvas, reference and renders use
+    pub(super) fn palette_selector(&mut self, ui: &mut egui::Ui) {
+        let mut active = self.active_palette;
+        egui::ComboBox::from_id_salt("active_palette")
+            .selected_text(format!("Palette: {}", self.active_palette_name()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut active, None, "Base");
+                for (k, palette) in self.palettes.iter().enumerate() {
+                    ui.selectable_value(&mut active, Some(k), &palette.name);
+                }
+            });
+        self.set_active_palette(active);
+    }
+
+    /// Renders the sheet once per palette (base included) next to `path`, named after each
+    /// palette, in the background
+    pub(super) fn export_all_variants(&mut self, ctx: &egui::Context) {
+        let Some(path) = platform::ask_save_path("Export variants as", "sheet.png") else {
+            return;
+        };
+        let base = path.strip_suffix(".png").unwrap_or(&path).to_string();
+        let mut variants = vec![(None, "base".to_string())];
+        variants.extend(
+            self.palettes
+                .iter()
+                .map(|palette| (Some(palette.clone()), palette.name.clone())),
+        );
+        let (frames, color_matrix) = (self.ref_matrix.clone(), self.color_matrix.clone());
+        let job = Job::new(ctx, "Exporting variants", move |progress| {
+            for (k, (palette, name)) in variants.iter().enumerate() {
+                if progress.cancelled() {
+                    return Err("Cancelled".to_string());
+                }
+                progress.set(k, variants.len());
+                let file_name: String = name
+                    .chars()
+                    .map(|c| {
+                        if c.is_alphanumeric() || c == '-' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                let bytes = encode_png(&render_sheet(&frames, &color_matrix, palette.as_ref()))?;
+                platform::write_file(&format!("{}_{}.png", base, file_name), &bytes)?;
+            }
+            Ok(Done::Exported {
+                files: variants.len(),
+            })
+        });
+        self.start_job(job);
+    }
+
+    pub(super) fn palettes_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_palettes;
+        let mut active = self.active_palette;
+        egui::Window::new("Palettes")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.radio_value(&mut active, None, "Base (reference PNG)");
+                let mut remove = None;
+                let mut duplicate = None;
+                for (k, palette) in self.palettes.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut active, Some(k), "");
+                        ui.text_edit_singleline(&mut palette.name);
+                        ui.label(format!("{} colors", palette.colors.len()));
+                        if ui.button("Duplicate").clicked() {
+                            duplicate = Some(k);
+                        }
+                        if ui.button("Remove").clicked() {
+                            remove = Some(k);
+                        }
+                    });
+                }
+                if let Some(k) = duplicate {
+                    let mut copy = self.palettes[k].clone();
+                    copy.name = format!("{} copy", copy.name);
+                    self.palettes.push(copy);
+                }
+                if let Some(k) = remove {
+                    self.palettes.remove(k);
+                    active = match active {
+                        Some(active) if active == k => None,
+                        Some(active) if active > k => Some(active - 1),
+                        active => active,
+                    };
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("New palette").clicked() {
+                        self.palettes.push(Palette {
+                            name: format!("Variant {}", self.palettes.len() + 1),
+                            colors: Vec::new(),
+                        });
+                        active = Some(self.palettes.len() - 1);
+                    }
+                    if ui.button("Export all variants...").clicked() {
+                        self.export_all_variants(ctx);
+                    }
+                });
+                ui.label(
+                    "Edit a variant's colors with Edit > Edit Reference Color while it's active",
+                );
+            });
+        self.set_active_palette(active);
+        self.show_palettes = open;
+    }
+}
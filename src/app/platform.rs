@@ -0,0 +1,282 @@
+//! File pickers and saving, implemented with native dialogs and the file system,
+//! or with the browser's file picker, downloads and local storage on the web.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// What a file being opened is for, so it can be handled once it's read
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OpenPurpose {
+    Reference,
+    Project,
+    OldReference,
+}
+
+pub struct OpenedFile {
+    pub purpose: OpenPurpose,
+    /// File name, or full path on native
+    pub name: String,
+    /// Only on native, where the file can be read again later
+    pub path: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+/// Files picked by the user. Native dialogs deliver them right away, the browser once it has
+/// read them, so they're collected here, along with read errors, and handled on the next frame.
+#[derive(Clone, Default)]
+pub struct FileIo {
+    opened: Rc<RefCell<Vec<Result<OpenedFile, String>>>>,
+}
+
+impl FileIo {
+    pub fn take_opened(&self) -> Vec<Result<OpenedFile, String>> {
+        std::mem::take(&mut self.opened.borrow_mut())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use super::{FileIo, OpenPurpose, OpenedFile};
+
+    impl FileIo {
+        /// Asks for a file with the given extension (without the dot) and queues its content
+        pub fn open(
+            &self,
+            _ctx: &egui::Context,
+            purpose: OpenPurpose,
+            title: &str,
+            extension: &str,
+        ) {
+            let pattern = format!("*.{}", extension);
+            let Some(path) = tinyfiledialogs::open_file_dialog(
+                title,
+                "",
+                Some((&[pattern.as_str()], extension)),
+            ) else {
+                return;
+            };
+            let file = std::fs::read(&path)
+                .map(|bytes| OpenedFile {
+                    purpose,
+                    name: path.clone(),
+                    path: Some(path),
+                    bytes,
+                })
+                .map_err(|e| e.to_string());
+            self.opened.borrow_mut().push(file);
+        }
+    }
+
+    /// Asks where to save, `default_name` is only a suggestion
+    pub fn ask_save_path(title: &str, default_name: &str) -> Option<String> {
+        tinyfiledialogs::save_file_dialog(title, default_name)
+    }
+
+    pub fn write_file(path: &str, bytes: &[u8]) -> Result<(), String> {
+        std::fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+
+    /// Projects live on disk on native, there's no separate storage
+    pub fn store_project(_name: &str, _json: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn stored_projects() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn load_stored_project(_name: &str) -> Option<String> {
+        None
+    }
+
+    pub fn remove_stored_project(_name: &str) {}
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use super::{FileIo, OpenPurpose, OpenedFile};
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast as _;
+
+    /// Prefix of the local storage keys holding projects
+    const STORAGE_PREFIX: &str = "pxref:";
+
+    fn window() -> web_sys::Window {
+        web_sys::window().expect("No window")
+    }
+
+    impl FileIo {
+        /// Opens the browser's file picker, the file is queued once the browser has read it
+        pub fn open(
+            &self,
+            ctx: &egui::Context,
+            purpose: OpenPurpose,
+            _title: &str,
+            extension: &str,
+        ) {
+            let Some(input) = window()
+                .document()
+                .and_then(|document| document.create_element("input").ok())
+                .and_then(|element| element.dyn_into::<web_sys::HtmlInputElement>().ok())
+            else {
+                return;
+            };
+            input.set_type("file");
+            input.set_accept(&format!(".{}", extension));
+            let opened = self.opened.clone();
+            let ctx = ctx.clone();
+            let picker = input.clone();
+            let on_change = Closure::once(move || {
+                let Some(file) = picker.files().and_then(|files| files.get(0)) else {
+                    return;
+                };
+                wasm_bindgen_futures::spawn_local(async move {
+                    let file = wasm_bindgen_futures::JsFuture::from(file.array_buffer())
+                        .await
+                        .map(|buffer| OpenedFile {
+                            purpose,
+                            name: file.name(),
+                            path: None,
+                            bytes: js_sys::Uint8Array::new(&buffer).to_vec(),
+                        })
+                        .map_err(|e| format!("{:?}", e));
+                    opened.borrow_mut().push(file);
+                    ctx.request_repaint();
+                });
+            });
+            input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+            on_change.forget();
+            input.click();
+        }
+    }
+
+    /// There's no save dialog in the browser, just ask for the downloaded file's name
+    pub fn ask_save_path(title: &str, default_name: &str) -> Option<String> {
+        window()
+            .prompt_with_message_and_default(title, default_name)
+            .ok()
+            .flatten()
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Downloads `bytes` as a file named `path`
+    pub fn write_file(path: &str, bytes: &[u8]) -> Result<(), String> {
+        let error = |e: wasm_bindgen::JsValue| format!("{:?}", e);
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(error)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+        let anchor = window()
+            .document()
+            .ok_or("No document")?
+            .create_element("a")
+            .map_err(error)?
+            .dyn_into::<web_sys::HtmlAnchorElement>()
+            .map_err(|_| "Not an anchor")?;
+        anchor.set_href(&url);
+        anchor.set_download(path);
+        anchor.click();
+        // Some browsers start the download after the click returns, revoking the URL right away
+        // would cancel it
+        let revoke = Closure::once(move || {
+            let _ = web_sys::Url::revoke_object_url(&url);
+        });
+        window()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                revoke.as_ref().unchecked_ref(),
+                60_000,
+            )
+            .map_err(error)?;
+        revoke.forget();
+        Ok(())
+    }
+
+    fn storage() -> Option<web_sys::Storage> {
+        window().local_storage().ok().flatten()
+    }
+
+    /// Keeps a copy of the project in the browser so it survives reloads without re-uploading it
+    pub fn store_project(name: &str, json: &str) -> Result<(), String> {
+        let storage = storage().ok_or("Browser storage is unavailable")?;
+        storage
+            .set_item(&format!("{}{}", STORAGE_PREFIX, name), json)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    pub fn stored_projects() -> Vec<String> {
+        let Some(storage) = storage() else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = (0..storage.length().unwrap_or(0))
+            .filter_map(|k| storage.key(k).ok().flatten())
+            .filter_map(|key| key.strip_prefix(STORAGE_PREFIX).map(str::to_string))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn load_stored_project(name: &str) -> Option<String> {
+        storage()?
+            .get_item(&format!("{}{}", STORAGE_PREFIX, name))
+            .ok()
+            .flatten()
+    }
+
+    pub fn remove_stored_project(name: &str) {
+        if let Some(storage) = storage() {
+            let _ = storage.remove_item(&format!("{}{}", STORAGE_PREFIX, name));
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::*;
+#[cfg(target_arch = "wasm32")]
+pub use web::*;
+
+/// Encodes the reference so a project can carry it where there's no file to point at
+pub fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write as _;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// `None` unless `hex` is pairs of hex digits
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|k| u8::from_str_radix(&hex[k..k + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let hex = to_hex(&bytes);
+        assert_eq!(hex.len(), 512);
+        assert!(hex.starts_with("000102"));
+        assert!(hex.ends_with("fdfeff"));
+        assert_eq!(from_hex(&hex), Some(bytes));
+        assert_eq!(from_hex("A0ff"), Some(vec![0xa0, 0xff]));
+        assert_eq!(from_hex(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn malformed_hex_is_rejected() {
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+        assert_eq!(from_hex("+f"), None);
+        assert_eq!(from_hex("0 "), None);
+        assert_eq!(from_hex("éé"), None);
+    }
+}
@@ -0,0 +1,164 @@
+use super::{get_checkerboard, TemplateApp};
+use egui::{Color32, Rgba, Vec2};
+
+/// What's drawn behind the transparent pixels of the preview
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, serde::Deserialize, serde::Serialize)]
+pub enum PreviewBackground {
+    #[default]
+    Checkerboard,
+    Color,
+}
+
+/// Animation preview settings, independent from the edited frame
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Preview {
+    pub scale: u32,
+    pub background: PreviewBackground,
+    pub color: Color32,
+    #[serde(skip)]
+    pub frame: usize,
+    #[serde(skip)]
+    pub playing: bool,
+    #[serde(skip)]
+    pub elapsed: f32,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            scale: 4,
+            background: PreviewBackground::Checkerboard,
+            color: Color32::WHITE,
+            frame: 0,
+            playing: true,
+            elapsed: 0.,
+        }
+    }
+}
+
+impl Preview {
+    pub const SCALES: [u32; 4] = [1, 2, 4, 8];
+    /// Size of a checkerboard tile, in preview pixels
+    const TILE: usize = 4;
+}
+
+impl TemplateApp {
+    /// Plays or pauses the animation in the preview, opening it to play if it's closed
+    pub(super) fn toggle_playback(&mut self) {
+        if self.show_preview {
+            self.preview.playing = !self.preview.playing;
+        } else {
+            self.show_preview = true;
+            self.preview.playing = true;
+        }
+    }
+
+    /// Separate OS window playing the animation at an integer scale. Falls back to a regular
+    /// window on backends without multiple viewports.
+    pub(super) fn preview_viewport(&mut self, ctx: &egui::Context) {
+        if !self.show_preview {
+            return;
+        }
+        let (width, height) = self.frame_size();
+        let inner_size = Vec2::new((width * 8) as f32 + 32., (height * 8) as f32 + 72.);
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("preview"),
+            egui::ViewportBuilder::default()
+                .with_title("Preview")
+                .with_inner_size(inner_size),
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    let mut open = true;
+                    egui::Window::new("Preview")
+                        .open(&mut open)
+                        .resizable(false)
+                        .show(ctx, |ui| self.preview_ui(ui));
+                    self.show_preview = open;
+                } else {
+                    egui::CentralPanel::default().show(ctx, |ui| self.preview_ui(ui));
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        self.show_preview = false;
+                    }
+                }
+            },
+        );
+    }
+
+    fn preview_ui(&mut self, ui: &mut egui::Ui) {
+        let frames_len = self.ref_matrix.len();
+        if self.preview.playing {
+            // Frame duration in milliseconds
+            self.preview.elapsed += ui.input(|i| i.stable_dt) * 1000.;
+            while self.preview.elapsed >= self.refresh_rate_fps as f32 {
+                self.preview.elapsed -= self.refresh_rate_fps as f32;
+                self.preview.frame += 1;
+            }
+            ui.ctx().request_repaint();
+        }
+        self.preview.frame %= frames_len;
+
+        ui.horizontal(|ui| {
+            if ui
+                .button(if self.preview.playing {
+                    "Pause"
+                } else {
+                    "Play"
+                })
+                .clicked()
+            {
+                self.preview.playing = !self.preview.playing;
+            }
+            ui.label(format!("{}/{}", self.preview.frame + 1, frames_len));
+            for scale in Preview::SCALES {
+                ui.selectable_value(&mut self.preview.scale, scale, format!("{}x", scale));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.preview.background,
+                PreviewBackground::Checkerboard,
+                "Checkerboard",
+            );
+            ui.radio_value(
+                &mut self.preview.background,
+                PreviewBackground::Color,
+                "Color",
+            );
+            ui.color_edit_button_srgba(&mut self.preview.color);
+        });
+
+        // One preview pixel is `scale` physical pixels so it stays crisp at any display scaling
+        let pixel = self.preview.scale as f32 / ui.ctx().pixels_per_point();
+        let frame = &self.ref_matrix[self.preview.frame];
+        let (width, height) = frame.size();
+        let (rect, _) = ui.allocate_exact_size(
+            Vec2::new(width as f32, height as f32) * pixel,
+            egui::Sense::hover(),
+        );
+        let mut texture = std::mem::take(&mut self.preview_texture);
+        let key = (
+            frame.version(),
+            self.colors_revision,
+            self.preview.background,
+            self.preview.color,
+        );
+        texture.update(ui.ctx(), "preview", key, (width, height), |x, y| {
+            let background = match self.preview.background {
+                PreviewBackground::Checkerboard => {
+                    get_checkerboard(x / Preview::TILE, y / Preview::TILE)
+                }
+                PreviewBackground::Color => self.preview.color,
+            };
+            match frame.get(x, y).and_then(|pos| self.ref_color(pos)) {
+                // Colors are premultiplied, so the background shows through by what's left of the alpha
+                Some(color) => (Rgba::from(color)
+                    + Rgba::from(background) * (1. - color.a() as f32 / 255.))
+                    .into(),
+                None => background,
+            }
+        });
+        texture.paint(&ui.painter_at(rect), rect);
+        self.preview_texture = texture;
+    }
+}
@@ -0,0 +1,168 @@
+use super::TemplateApp;
+
+/// Sequence of reference pixels going from dark to light
+#[derive(Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Ramp {
+    pub name: String,
+    pub refs: Vec<(usize, usize)>,
+}
+
+/// Ref `steps` positions further along (positive: lighter) the first ramp containing `pos`,
+/// stopping at the ends of the ramp. `None` if no ramp contains it.
+pub fn step_ref(ramps: &[Ramp], pos: (usize, usize), steps: i32) -> Option<(usize, usize)> {
+    ramps.iter().find_map(|ramp| {
+        let index = ramp.refs.iter().position(|p| *p == pos)? as i32;
+        let target = (index + steps).clamp(0, ramp.refs.len() as i32 - 1);
+        Some(ramp.refs[target as usize])
+    })
+}
+
+impl TemplateApp {
+    /// Moves the ref of `cell` (and its symmetry counterparts) `steps` along its ramp
+    pub(super) fn shade_cell(&mut self, cell: (usize, usize), steps: i32) {
+        let size = self.frame_size();
+        for (target, _) in self.symmetry.cells(cell, size) {
+            let current = self.ref_matrix[self.current_frame].get(target.0, target.1);
+            if let Some(shaded) = current.and_then(|pos| step_ref(&self.ramps, pos, steps)) {
+                self.ref_matrix[self.current_frame].set(target.0, target.1, Some(shaded));
+            }
+        }
+    }
+
+    /// Shades every selected cell, or the whole frame without a selection
+    pub(super) fn shade_selection(&mut self, steps: i32) {
+        let size = self.frame_size();
+        for x in 0..size.0 {
+            for y in 0..size.1 {
+                if self
+                    .selection
+                    .as_ref()
+                    .map_or(true, |selection| selection.contains(x, y))
+                {
+                    if let Some(pos) = self.ref_matrix[self.current_frame].get(x, y) {
+                        self.ref_matrix[self.current_frame].set(
+                            x,
+                            y,
+                            step_ref(&self.ramps, pos, steps).or(Some(pos)),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shade brush: primary button lightens, secondary darkens, once per cell per stroke
+    pub(super) fn update_shading(
+        &mut self,
+        ctx: &egui::Context,
+        hovered_cell: Option<(usize, usize)>,
+    ) {
+        if self.tool != super::Tool::Shade {
+            return;
+        }
+        let (primary, secondary) =
+            ctx.input(|i| (i.pointer.primary_down(), i.pointer.secondary_down()));
+        if !primary && !secondary {
+            self.shaded_cells.clear();
+            return;
+        }
+        if let Some(cell) = hovered_cell {
+            if !self.shaded_cells.contains(&cell) {
+                self.shaded_cells.push(cell);
+                self.shade_cell(cell, if primary { 1 } else { -1 });
+            }
+        }
+    }
+
+    pub(super) fn ramps_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_ramps;
+        egui::Window::new("Shading Ramps").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.label("Refs go from dark to light. The shade tool lightens with the left button and darkens with the right one.");
+            let mut remove = None;
+            for (k, ramp) in self.ramps.iter_mut().enumerate() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut ramp.name);
+                    if ui.button("Remove").clicked() {
+                        remove = Some(k);
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    let mut remove_ref = None;
+                    for (i, (x, y)) in ramp.refs.iter().enumerate() {
+                        if ui.small_button(format!("({}, {}) ✖", x, y)).on_hover_text("Remove from ramp").clicked() {
+                            remove_ref = Some(i);
+                        }
+                    }
+                    if let Some(i) = remove_ref {
+                        ramp.refs.remove(i);
+                    }
+                    if let Some(selected) = self.selected_ref {
+                        if !ramp.refs.contains(&selected) && ui.small_button("+ selected ref").clicked() {
+                            ramp.refs.push(selected);
+                        }
+                    }
+                });
+            }
+            if let Some(k) = remove {
+                self.ramps.remove(k);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("From row");
+                ui.add(egui::DragValue::new(&mut self.ramp_row.0).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut self.ramp_row.1).prefix("y: "));
+                ui.add(egui::DragValue::new(&mut self.ramp_row.2).prefix("length: ").range(1..=usize::MAX));
+                if ui.button("Add ramp").clicked() {
+                    let (x, y, length) = self.ramp_row;
+                    let width = self.reference_size().0;
+                    self.ramps.push(Ramp {
+                        name: format!("Ramp {}", self.ramps.len() + 1),
+                        refs: (x..(x + length).min(width)).map(|x| (x, y)).collect(),
+                    });
+                }
+            });
+            if ui.button("New empty ramp").clicked() {
+                self.ramps.push(Ramp { name: format!("Ramp {}", self.ramps.len() + 1), refs: Vec::new() });
+            }
+        });
+        self.show_ramps = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramps() -> Vec<Ramp> {
+        vec![
+            Ramp {
+                name: "skin".into(),
+                refs: vec![(0, 0), (1, 0), (2, 0)],
+            },
+            Ramp {
+                name: "hair".into(),
+                refs: vec![(0, 1), (1, 1)],
+            },
+        ]
+    }
+
+    #[test]
+    fn steps_along_the_ramp() {
+        let ramps = ramps();
+        assert_eq!(step_ref(&ramps, (1, 0), 1), Some((2, 0)));
+        assert_eq!(step_ref(&ramps, (1, 0), -1), Some((0, 0)));
+        assert_eq!(step_ref(&ramps, (0, 1), 1), Some((1, 1)));
+        assert_eq!(step_ref(&ramps, (1, 0), 0), Some((1, 0)));
+        assert_eq!(step_ref(&ramps, (5, 5), 1), None);
+    }
+
+    #[test]
+    fn stepping_stops_at_both_ends() {
+        let ramps = ramps();
+        assert_eq!(step_ref(&ramps, (2, 0), 1), Some((2, 0)));
+        assert_eq!(step_ref(&ramps, (0, 0), -1), Some((0, 0)));
+        assert_eq!(step_ref(&ramps, (1, 0), 10), Some((2, 0)));
+        assert_eq!(step_ref(&ramps, (1, 1), -10), Some((0, 1)));
+    }
+}
@@ -0,0 +1,103 @@
+use super::{platform, Deferred, TemplateApp};
+
+/// How many entries File > Open Recent keeps
+const MAX_RECENT: usize = 10;
+
+/// Moves `path` to the front of the list, dropping duplicates and the oldest entries
+pub fn push_recent(recent: &mut Vec<String>, path: &str) {
+    recent.retain(|p| p != path);
+    recent.insert(0, path.to_string());
+    recent.truncate(MAX_RECENT);
+}
+
+impl TemplateApp {
+    /// Opens a project or reference from its path, forgetting it if it can't be read
+    pub(super) fn open_recent(&mut self, ctx: &egui::Context, path: &str) {
+        if path.ends_with(".pxref") {
+            self.after_discard(ctx, Deferred::OpenProject(path.to_string()));
+        } else {
+            self.open_png(ctx, path);
+        }
+    }
+
+    /// Reopens the project open when the app was last closed, if the user asked for it. True if
+    /// it's being reopened.
+    pub(super) fn reopen_last_project(&mut self, ctx: &egui::Context) -> bool {
+        // The frames aren't persisted, so the project is only still open if it's reopened
+        let project_path = self.project_path.take();
+        if !self.reopen_last {
+            return false;
+        }
+        let Some(path) = project_path else {
+            return false;
+        };
+        if let Some(json) = platform::load_stored_project(&path) {
+            self.load_project_json(ctx, json, &path);
+        } else if std::path::Path::new(&path).exists() {
+            self.open_ref(ctx, &path);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub(super) fn recent_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Open Recent", |ui| {
+            if self.recent_files.is_empty() {
+                ui.label("No recent files");
+            }
+            let mut open = None;
+            for path in &self.recent_files {
+                let name = std::path::Path::new(path)
+                    .file_name()
+                    .map_or(path.clone(), |name| name.to_string_lossy().to_string());
+                if ui.button(name).on_hover_text(path).clicked() {
+                    open = Some(path.clone());
+                }
+            }
+            if let Some(path) = open {
+                self.open_recent(ui.ctx(), &path);
+                ui.close_menu();
+            }
+            ui.separator();
+            ui.checkbox(&mut self.reopen_last, "Reopen last project on launch");
+            if ui
+                .add_enabled(
+                    !self.recent_files.is_empty(),
+                    egui::Button::new("Clear Recent"),
+                )
+                .clicked()
+            {
+                self.recent_files.clear();
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Web counterpart of Open Recent: projects kept in browser storage
+    pub(super) fn stored_projects_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Open Stored", |ui| {
+            let names = platform::stored_projects();
+            if names.is_empty() {
+                ui.label("No projects in browser storage");
+            }
+            for name in names {
+                ui.horizontal(|ui| {
+                    if ui.button(&name).clicked() {
+                        self.after_discard(ui.ctx(), Deferred::OpenStored(name.clone()));
+                        ui.close_menu();
+                    }
+                    if ui
+                        .small_button("✖")
+                        .on_hover_text("Remove from browser storage")
+                        .clicked()
+                    {
+                        platform::remove_stored_project(&name);
+                    }
+                });
+            }
+            ui.separator();
+            ui.checkbox(&mut self.reopen_last, "Reopen last project on launch");
+        });
+    }
+}
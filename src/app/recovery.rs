@@ -0,0 +1,211 @@
+use super::{
+    encode_png, matrix_to_image, platform, project_reference, Deferred, Done, Job, PxRefFile,
+    TemplateApp,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// How many snapshots are kept, the newest is `recovery-0.json`
+const SNAPSHOTS: usize = 3;
+/// Time between snapshots while the project keeps changing
+const INTERVAL: Duration = Duration::from_secs(30);
+
+/// Copy of the whole project written in the background in case the app doesn't close cleanly
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch
+    pub saved_at: u64,
+    pub project_path: Option<String>,
+    pub project: PxRefFile,
+}
+
+impl Snapshot {
+    /// "5 min ago" style age of the snapshot
+    pub fn age(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        match now.saturating_sub(self.saved_at) {
+            secs if secs < 60 => "just now".to_string(),
+            secs if secs < 3600 => format!("{} min ago", secs / 60),
+            secs if secs < 86400 => format!("{} h ago", secs / 3600),
+            secs => format!("{} days ago", secs / 86400),
+        }
+    }
+}
+
+/// Where snapshots go, next to eframe's own storage
+#[cfg(not(target_arch = "wasm32"))]
+fn recovery_dir() -> Option<PathBuf> {
+    eframe::storage_dir("Reference").map(|dir| dir.join("recovery"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn recovery_dir() -> Option<PathBuf> {
+    None
+}
+
+fn snapshot_path(dir: &std::path::Path, k: usize) -> PathBuf {
+    dir.join(format!("recovery-{}.json", k))
+}
+
+/// Existing snapshots, newest first
+pub fn read_snapshots() -> Vec<Snapshot> {
+    let Some(dir) = recovery_dir() else {
+        return Vec::new();
+    };
+    (0..SNAPSHOTS)
+        .filter_map(|k| std::fs::read_to_string(snapshot_path(&dir, k)).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect()
+}
+
+/// Writes `snapshot` as the newest one, shifting the others and dropping the oldest
+fn write_snapshot(snapshot: &Snapshot) -> std::io::Result<()> {
+    let Some(dir) = recovery_dir() else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+    for k in (1..SNAPSHOTS).rev() {
+        let from = snapshot_path(&dir, k - 1);
+        if from.exists() {
+            std::fs::rename(from, snapshot_path(&dir, k))?;
+        }
+    }
+    let json = serde_json::to_string(snapshot).map_err(std::io::Error::other)?;
+    std::fs::write(snapshot_path(&dir, 0), json)
+}
+
+pub fn clear_snapshots() {
+    if let Some(dir) = recovery_dir() {
+        for k in 0..SNAPSHOTS {
+            let _ = std::fs::remove_file(snapshot_path(&dir, k));
+        }
+    }
+}
+
+impl TemplateApp {
+    pub(super) fn is_empty_project(&self) -> bool {
+        self.ref_matrix
+            .iter()
+            .all(|frame| frame.refs().next().is_none())
+    }
+
+    /// Looks for snapshots left by a previous session and offers to restore them
+    pub(super) fn check_recovery(&mut self) {
+        self.snapshots = read_snapshots();
+        self.show_recovery = !self.snapshots.is_empty();
+    }
+
+    /// Snapshots the project every `INTERVAL` if it changed since the last one
+    pub(super) fn autosave(&mut self) {
+        // Don't overwrite the previous session's snapshots before the user decides about them
+        if self.show_recovery || self.last_autosave.elapsed() < INTERVAL {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        if self.project_path.is_none() && self.is_empty_project() {
+            return;
+        }
+        let unchanged = self.autosaved.as_ref().is_some_and(|(project, revision)| {
+            self.project_matches(project) && *revision == self.colors_revision
+        });
+        if unchanged || recovery_dir().is_none() {
+            return;
+        }
+        let project = self.project_file();
+        self.autosaved = Some((project.clone(), self.colors_revision));
+        let mut snapshot = Snapshot {
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            project_path: self.project_path.clone(),
+            project,
+        };
+        // Colors edited in the app aren't in the PNG on disk yet, the snapshot carries them
+        let modified = self
+            .reference_modified
+            .then(|| (self.color_matrix.clone(), self.reference_pixels.clone()));
+        // Encoding and writing a large project takes a while, keep it off the UI thread
+        std::thread::spawn(move || {
+            if let Some((matrix, pixels)) = modified {
+                match encode_png(&matrix_to_image(&matrix, &pixels)) {
+                    Ok(bytes) => snapshot.project.ref_png_data = Some(platform::to_hex(&bytes)),
+                    Err(e) => log::warn!("Failed to encode the reference for recovery: {}", e),
+                }
+            }
+            if let Err(e) = write_snapshot(&snapshot) {
+                log::warn!("Failed to write recovery snapshot: {}", e);
+            }
+        });
+    }
+
+    /// Removes the snapshots when the open project matches its saved file, so there's nothing to recover
+    pub(super) fn clear_recovery_if_saved(&self) {
+        if !self.has_unsaved_changes() {
+            clear_snapshots();
+        }
+    }
+
+    pub(super) fn recovery_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_recovery;
+        let mut restore = None;
+        egui::Window::new("Recover Unsaved Work")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    "The app didn't close with everything saved. Restore one of these snapshots?",
+                );
+                ui.separator();
+                egui::Grid::new("recovery_snapshots")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (k, snapshot) in self.snapshots.iter().enumerate() {
+                            let name = snapshot
+                                .project_path
+                                .as_deref()
+                                .and_then(|path| std::path::Path::new(path).file_name())
+                                .map_or("Unsaved project".to_string(), |name| {
+                                    name.to_string_lossy().to_string()
+                                });
+                            ui.label(name);
+                            ui.label(format!("{} frames", snapshot.project.ref_matrix.len()));
+                            ui.label(snapshot.age());
+                            if ui.button("Restore").clicked() {
+                                restore = Some(k);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+                if ui.button("Discard all").clicked() {
+                    clear_snapshots();
+                    self.snapshots.clear();
+                }
+            });
+        if let Some(k) = restore {
+            self.after_discard(ctx, Deferred::RestoreSnapshot(k));
+        }
+        self.show_recovery = open && !self.snapshots.is_empty();
+    }
+
+    pub(super) fn restore_snapshot(&mut self, ctx: &egui::Context, k: usize) {
+        let Some(snapshot) = self.snapshots.get(k).cloned() else {
+            return;
+        };
+        let job = Job::new(ctx, "Restoring snapshot", move |_| {
+            let reference = project_reference(&snapshot.project)?;
+            Ok(Done::Project {
+                name: snapshot.project_path,
+                path: None,
+                data: snapshot.project,
+                reference,
+                snapshot: true,
+            })
+        });
+        self.start_job(job);
+    }
+}
@@ -0,0 +1,193 @@
+use super::{
+    encode_png, image_to_matrix, platform, with_extension, ColorMatrix, Level, TemplateApp,
+};
+use egui::Color32;
+use image::{Rgba, RgbaImage};
+
+/// A decoded reference PNG
+pub struct ReferenceImage {
+    /// Colors the reference is drawn with
+    pub matrix: ColorMatrix,
+    /// The pixels exactly as decoded, colors are premultiplied in `matrix` which loses precision
+    /// where they're semi-transparent
+    pub pixels: RgbaImage,
+}
+
+impl ReferenceImage {
+    pub fn new(pixels: RgbaImage) -> Self {
+        Self {
+            matrix: image_to_matrix(&pixels),
+            pixels,
+        }
+    }
+}
+
+/// Converts the reference back to an image. Pixels still showing the color they were loaded
+/// with keep their `original` RGBA, edited ones are unmultiplied from the matrix and
+/// transparent ones are fully transparent.
+pub fn matrix_to_image(matrix: &ColorMatrix, original: &RgbaImage) -> RgbaImage {
+    let (width, height) = (matrix.len(), matrix.first().map_or(0, |col| col.len()));
+    let mut img = RgbaImage::new(width as u32, height as u32);
+    for (x, col) in matrix.iter().enumerate() {
+        for (y, color) in col.iter().enumerate() {
+            let loaded = original
+                .get_pixel_checked(x as u32, y as u32)
+                .map(|pixel| pixel.0);
+            let unchanged = loaded.filter(|[r, g, b, a]| match color {
+                Some(color) => *a > 0 && Color32::from_rgba_unmultiplied(*r, *g, *b, *a) == *color,
+                None => *a == 0,
+            });
+            let pixel = unchanged.unwrap_or_else(|| {
+                color.map_or([0, 0, 0, 0], |color| color.to_srgba_unmultiplied())
+            });
+            img.put_pixel(x as u32, y as u32, Rgba(pixel));
+        }
+    }
+    img
+}
+
+impl TemplateApp {
+    /// Writes the reference to its PNG, or asks for a new file if `save_as` or none is loaded.
+    /// True if saved.
+    pub(super) fn save_reference(&mut self, save_as: bool) -> bool {
+        let path = match (&self.file_path, save_as) {
+            (Some(path), false) => Some(path.clone()),
+            _ => platform::ask_save_path("Save reference as", "reference.png")
+                .map(|path| with_extension(path, "png")),
+        };
+        let Some(path) = path else { return false };
+        let img = matrix_to_image(&self.color_matrix, &self.reference_pixels);
+        match encode_png(&img).and_then(|bytes| platform::write_file(&path, &bytes)) {
+            Ok(()) => {
+                self.file_path = Some(path);
+                self.reference_modified = false;
+                self.reference_pixels = img;
+                true
+            }
+            Err(e) => {
+                self.notify(Level::Error, "Failed to Save Reference", &e);
+                false
+            }
+        }
+    }
+
+    /// Color picker for the selected reference pixel, every frame using it updates immediately
+    pub(super) fn reference_color_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_reference_color;
+        egui::Window::new("Reference Color")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let size = self.reference_size();
+                let Some((x, y)) = self
+                    .selected_ref
+                    .filter(|pos| pos.0 < size.0 && pos.1 < size.1)
+                else {
+                    ui.label("Click a pixel on the reference to edit its color");
+                    return;
+                };
+                ui.label(format!(
+                    "Pixel ({}, {}) in palette {}",
+                    x,
+                    y,
+                    self.active_palette_name()
+                ));
+                let mut color = self.ref_color((x, y)).unwrap_or(Color32::TRANSPARENT);
+                let mut edit: Option<Option<Color32>> = None;
+                ui.horizontal(|ui| {
+                    if egui::color_picker::color_edit_button_srgba(
+                        ui,
+                        &mut color,
+                        egui::color_picker::Alpha::OnlyBlend,
+                    )
+                    .changed()
+                    {
+                        edit = Some((color.a() > 0).then_some(color));
+                    }
+                    if ui.button("Make transparent").clicked() {
+                        edit = Some(None);
+                    }
+                    if let Some(palette) =
+                        self.active_palette.and_then(|k| self.palettes.get_mut(k))
+                    {
+                        if ui
+                            .add_enabled(
+                                palette.get((x, y)).is_some(),
+                                egui::Button::new("Reset to base"),
+                            )
+                            .clicked()
+                        {
+                            palette.reset((x, y));
+                            self.colors_revision += 1;
+                        }
+                    }
+                });
+                // Variants keep their own colors, only the base palette writes to the reference PNG
+                if let Some(new_color) = edit {
+                    match self.active_palette.and_then(|k| self.palettes.get_mut(k)) {
+                        Some(palette) => {
+                            palette.set((x, y), new_color);
+                            self.colors_revision += 1;
+                        }
+                        None => {
+                            self.color_matrix[x][y] = new_color;
+                            self.reference_modified = true;
+                            self.colors_revision += 1;
+                            // Refs to this pixel may have just become transparent, or stopped being
+                            self.run_validation();
+                        }
+                    }
+                }
+                let [r, g, b, a] = color.to_srgba_unmultiplied();
+                ui.label(format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save PNG").clicked() {
+                        self.save_reference(false);
+                    }
+                    if ui.button("Save PNG As...").clicked() {
+                        self.save_reference(true);
+                    }
+                    if self.reference_modified {
+                        ui.label("(modified)");
+                    }
+                });
+            });
+        self.show_reference_color = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded() -> RgbaImage {
+        let mut img = RgbaImage::new(3, 1);
+        img.put_pixel(0, 0, Rgba([200, 100, 50, 77]));
+        img.put_pixel(1, 0, Rgba([10, 20, 30, 0]));
+        img.put_pixel(2, 0, Rgba([1, 2, 3, 255]));
+        img
+    }
+
+    #[test]
+    fn untouched_pixels_save_exactly_as_loaded() {
+        let reference = ReferenceImage::new(loaded());
+        assert_eq!(reference.matrix[1][0], None);
+        assert_eq!(
+            matrix_to_image(&reference.matrix, &reference.pixels),
+            loaded()
+        );
+    }
+
+    #[test]
+    fn edited_pixels_save_their_new_color() {
+        let mut reference = ReferenceImage::new(loaded());
+        reference.matrix[0][0] = Some(Color32::from_rgba_unmultiplied(9, 8, 7, 255));
+        reference.matrix[1][0] = Some(Color32::from_rgb(4, 5, 6));
+        reference.matrix[2][0] = None;
+        let img = matrix_to_image(&reference.matrix, &reference.pixels);
+        assert_eq!(img.get_pixel(0, 0).0, [9, 8, 7, 255]);
+        assert_eq!(img.get_pixel(1, 0).0, [4, 5, 6, 255]);
+        assert_eq!(img.get_pixel(2, 0).0, [0, 0, 0, 0]);
+    }
+}
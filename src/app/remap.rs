@@ -0,0 +1,208 @@
+use super::{ColorMatrix, Frame, OpenPurpose, RefRegion, TemplateApp};
+use egui::Color32;
+use std::collections::HashMap;
+
+/// Colors of a pixel and its 8 neighbors, row by row, `None` for transparent or outside
+type Neighborhood = [Option<Color32>; 9];
+
+fn neighborhood(matrix: &ColorMatrix, pos: (usize, usize)) -> Neighborhood {
+    let mut colors = [None; 9];
+    for (k, color) in colors.iter_mut().enumerate() {
+        let (dx, dy) = ((k % 3) as isize - 1, (k / 3) as isize - 1);
+        let (x, y) = (pos.0 as isize + dx, pos.1 as isize + dy);
+        if x >= 0 && y >= 0 {
+            *color = matrix
+                .get(x as usize)
+                .and_then(|col| col.get(y as usize))
+                .copied()
+                .flatten();
+        }
+    }
+    colors
+}
+
+/// Matches the opaque pixels of `old` to where they moved in `new` by comparing each pixel's
+/// 3x3 neighborhood. Pixels matching several places get the candidate moved by the same offset
+/// as most unambiguous matches, so parts that moved as a block stay together. Pixels that can't
+/// be found aren't in the map.
+pub fn match_layout(
+    old: &ColorMatrix,
+    new: &ColorMatrix,
+) -> HashMap<(usize, usize), (usize, usize)> {
+    let mut candidates: HashMap<Neighborhood, Vec<(usize, usize)>> = HashMap::new();
+    for (x, col) in new.iter().enumerate() {
+        for (y, color) in col.iter().enumerate() {
+            if color.is_some() {
+                candidates
+                    .entry(neighborhood(new, (x, y)))
+                    .or_default()
+                    .push((x, y));
+            }
+        }
+    }
+
+    let mut mapping = HashMap::new();
+    let mut ambiguous = Vec::new();
+    let mut offsets: HashMap<(isize, isize), usize> = HashMap::new();
+    for (x, col) in old.iter().enumerate() {
+        for (y, color) in col.iter().enumerate() {
+            if color.is_none() {
+                continue;
+            }
+            match candidates
+                .get(&neighborhood(old, (x, y)))
+                .map(|found| found.as_slice())
+            {
+                Some([single]) => {
+                    mapping.insert((x, y), *single);
+                    *offsets
+                        .entry((
+                            single.0 as isize - x as isize,
+                            single.1 as isize - y as isize,
+                        ))
+                        .or_default() += 1;
+                }
+                Some(found) if !found.is_empty() => ambiguous.push(((x, y), found)),
+                _ => {}
+            }
+        }
+    }
+
+    for (pos, found) in ambiguous {
+        let best = found.iter().max_by_key(|candidate| {
+            offsets
+                .get(&(
+                    candidate.0 as isize - pos.0 as isize,
+                    candidate.1 as isize - pos.1 as isize,
+                ))
+                .copied()
+                .unwrap_or(0)
+        });
+        if let Some(best) = best {
+            mapping.insert(pos, *best);
+        }
+    }
+    mapping
+}
+
+/// Rewrites every ref in every frame through `map`, refs it returns `None` for are kept.
+/// Returns how many cells changed.
+pub fn remap_frames(
+    frames: &mut [Frame],
+    map: impl Fn((usize, usize)) -> Option<(usize, usize)>,
+) -> usize {
+    frames.iter_mut().map(|frame| frame.map_refs(&map)).sum()
+}
+
+/// Moves `pos` by `offset` if it's inside `region`
+pub fn offset_ref(
+    region: &RefRegion,
+    offset: (i32, i32),
+    pos: (usize, usize),
+) -> Option<(usize, usize)> {
+    region.local(pos)?;
+    let (x, y) = (
+        pos.0 as i64 + offset.0 as i64,
+        pos.1 as i64 + offset.1 as i64,
+    );
+    (x >= 0 && y >= 0).then_some((x as usize, y as usize))
+}
+
+impl TemplateApp {
+    /// Moves every ref from where it was in `old` to where the same pixel is in the loaded reference
+    pub(super) fn remap_from_old(&mut self, old: &ColorMatrix) {
+        let mapping = match_layout(old, &self.color_matrix);
+        let used: Vec<(usize, usize)> = self.ref_matrix.iter().flat_map(Frame::refs).collect();
+        let unmatched = used.iter().filter(|pos| !mapping.contains_key(pos)).count();
+        let changed = remap_frames(&mut self.ref_matrix, |pos| mapping.get(&pos).copied());
+        self.remap_result = format!(
+            "Moved {} cells, {} cells could not be matched",
+            changed, unmatched
+        );
+        self.run_validation();
+    }
+
+    pub(super) fn remap_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_remap;
+        egui::Window::new("Remap References")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Move the refs pointing into a region of the reference");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.remap_region.min.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut self.remap_region.min.1).prefix("y: "));
+                    ui.add(egui::DragValue::new(&mut self.remap_region.size.0).prefix("w: "));
+                    ui.add(egui::DragValue::new(&mut self.remap_region.size.1).prefix("h: "));
+                    if ui.button("Whole reference").clicked() {
+                        self.remap_region = RefRegion {
+                            min: (0, 0),
+                            size: self.reference_size(),
+                        };
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Offset");
+                    ui.add(egui::DragValue::new(&mut self.remap_offset.0).prefix("dx: "));
+                    ui.add(egui::DragValue::new(&mut self.remap_offset.1).prefix("dy: "));
+                    if ui.button("Apply").clicked() {
+                        let (region, offset) = (self.remap_region, self.remap_offset);
+                        let changed = remap_frames(&mut self.ref_matrix, |pos| {
+                            offset_ref(&region, offset, pos)
+                        });
+                        self.remap_result = format!("Moved {} cells", changed);
+                        self.run_validation();
+                    }
+                });
+                ui.separator();
+                ui.label("Match the previous version of the reference to the loaded one");
+                if ui.button("Pick old reference PNG...").clicked() {
+                    self.file_io
+                        .open(ctx, OpenPurpose::OldReference, "Old reference", "png");
+                }
+                if !self.remap_result.is_empty() {
+                    ui.separator();
+                    ui.label(&self.remap_result);
+                }
+            });
+        self.show_remap = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(pixels: &[((usize, usize), Color32)]) -> ColorMatrix {
+        let mut matrix = vec![vec![None; 8]; 8];
+        for (pos, color) in pixels {
+            matrix[pos.0][pos.1] = Some(*color);
+        }
+        matrix
+    }
+
+    #[test]
+    fn moved_pixels_follow_their_block() {
+        let block = [Color32::RED, Color32::GREEN, Color32::BLUE, Color32::YELLOW];
+        let at = |origin: (usize, usize)| {
+            (0..4).map(move |k| ((origin.0 + k % 2, origin.1 + k / 2), block[k]))
+        };
+        let mut old: Vec<_> = at((1, 1)).collect();
+        old.push(((1, 5), Color32::WHITE));
+        old.push(((5, 5), Color32::BROWN));
+        // The block moved by (2, 1), the lone white pixel did too but also appears elsewhere
+        let mut new: Vec<_> = at((3, 2)).collect();
+        new.push(((3, 6), Color32::WHITE));
+        new.push(((6, 1), Color32::WHITE));
+
+        let mapping = match_layout(&reference(&old), &reference(&new));
+        for k in 0..4 {
+            let (x, y) = (1 + k % 2, 1 + k / 2);
+            assert_eq!(mapping.get(&(x, y)), Some(&(x + 2, y + 1)));
+        }
+        assert_eq!(mapping.get(&(1, 5)), Some(&(3, 6)));
+        // Gone from the new reference
+        assert_eq!(mapping.get(&(5, 5)), None);
+        assert_eq!(mapping.len(), 5);
+    }
+}
@@ -0,0 +1,486 @@
+use super::{Frame, Panel, RefMatrix, TemplateApp, Tool};
+use egui::{Color32, Painter, Pos2, Stroke};
+
+/// Prefix marking clipboard text as canvas cells, so pasting unrelated text is ignored
+const CLIP_PREFIX: &str = "pxref-clip:";
+
+/// Cells picked with the marquee or lasso tools, as a mask over the frame (indexed `[x][y]`)
+#[derive(Clone)]
+pub struct Selection {
+    pub mask: Vec<Vec<bool>>,
+}
+
+impl Selection {
+    pub fn all(size: (usize, usize)) -> Self {
+        Self {
+            mask: vec![vec![true; size.1]; size.0],
+        }
+    }
+
+    /// Rectangle with corners `a` and `b`, inclusive and in any order
+    pub fn rect(size: (usize, usize), a: (usize, usize), b: (usize, usize)) -> Self {
+        let mut mask = vec![vec![false; size.1]; size.0];
+        for (x, col) in mask.iter_mut().enumerate() {
+            for (y, cell) in col.iter_mut().enumerate() {
+                *cell = x >= a.0.min(b.0)
+                    && x <= a.0.max(b.0)
+                    && y >= a.1.min(b.1)
+                    && y <= a.1.max(b.1);
+            }
+        }
+        Self { mask }
+    }
+
+    /// Freeform selection: the cells on the traced path plus every cell whose center lies inside it
+    pub fn lasso(size: (usize, usize), path: &[(usize, usize)]) -> Self {
+        let mut mask = vec![vec![false; size.1]; size.0];
+        let points: Vec<(f32, f32)> = path
+            .iter()
+            .map(|p| (p.0 as f32 + 0.5, p.1 as f32 + 0.5))
+            .collect();
+        for (x, col) in mask.iter_mut().enumerate() {
+            for (y, cell) in col.iter_mut().enumerate() {
+                *cell = path.contains(&(x, y))
+                    || point_in_polygon((x as f32 + 0.5, y as f32 + 0.5), &points);
+            }
+        }
+        Self { mask }
+    }
+
+    /// Same selection over a frame of `size`, which the mask may have been made on a frame
+    /// of another size than
+    pub fn clipped(&self, size: (usize, usize)) -> Self {
+        Self {
+            mask: (0..size.0)
+                .map(|x| (0..size.1).map(|y| self.contains(x, y)).collect())
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.mask
+            .get(x)
+            .and_then(|col| col.get(y))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Inclusive bounding box `(min, max)` of the selected cells
+    pub fn bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        let mut bounds: Option<((usize, usize), (usize, usize))> = None;
+        for (x, col) in self.mask.iter().enumerate() {
+            for (y, _) in col.iter().enumerate().filter(|(_, selected)| **selected) {
+                bounds = Some(match bounds {
+                    None => ((x, y), (x, y)),
+                    Some((min, max)) => {
+                        ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+                    }
+                });
+            }
+        }
+        bounds
+    }
+}
+
+// Even-odd rule
+fn point_in_polygon(point: (f32, f32), polygon: &[(f32, f32)]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.1 > point.1) != (b.1 > point.1)
+            && point.0 < (b.0 - a.0) * (point.1 - a.1) / (b.1 - a.1) + a.0
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Copied cells, local to the bounding box of the selection they were taken from
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+pub struct Clip {
+    /// Top-left cell the clip was copied from, so pasting puts it back in place
+    pub origin: (usize, usize),
+    pub mask: Vec<Vec<bool>>,
+    pub refs: RefMatrix,
+}
+
+impl Clip {
+    pub fn from_selection(matrix: &Frame, selection: &Selection) -> Option<Self> {
+        let selection = selection.clipped(matrix.size());
+        let (min, max) = selection.bounds()?;
+        let (width, height) = (max.0 + 1 - min.0, max.1 + 1 - min.1);
+        let mut mask = vec![vec![false; height]; width];
+        let mut refs = vec![vec![None; height]; width];
+        for x in 0..width {
+            for y in 0..height {
+                if selection.contains(min.0 + x, min.1 + y) {
+                    mask[x][y] = true;
+                    refs[x][y] = matrix.get(min.0 + x, min.1 + y);
+                }
+            }
+        }
+        Some(Self {
+            origin: min,
+            mask,
+            refs,
+        })
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (
+            self.mask.len(),
+            self.mask.first().map_or(0, |col| col.len()),
+        )
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}{}",
+            CLIP_PREFIX,
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+
+    pub fn from_text(text: &str) -> Option<Self> {
+        let clip: Clip = serde_json::from_str(text.trim().strip_prefix(CLIP_PREFIX)?).ok()?;
+        let (width, height) = clip.size();
+        let consistent = clip.refs.len() == width
+            && clip.mask.iter().all(|col| col.len() == height)
+            && clip.refs.iter().all(|col| col.len() == height);
+        (width > 0 && height > 0 && consistent).then_some(clip)
+    }
+}
+
+/// Pasted clip hovering over the canvas until it's committed
+pub struct Floating {
+    pub clip: Clip,
+    /// Canvas cell of the clip's top-left corner, may be partially off canvas
+    pub offset: (i32, i32),
+}
+
+impl Floating {
+    pub fn new(clip: Clip) -> Self {
+        let offset = (clip.origin.0 as i32, clip.origin.1 as i32);
+        Self { clip, offset }
+    }
+
+    /// Canvas cells covered by the clip with the ref they get (empty cells don't overwrite)
+    pub fn cells(&self) -> impl Iterator<Item = ((i32, i32), (usize, usize))> + '_ {
+        self.clip.refs.iter().enumerate().flat_map(move |(x, col)| {
+            col.iter().enumerate().filter_map(move |(y, cell)| {
+                let cell = (*cell)?;
+                self.clip.mask[x][y]
+                    .then_some(((self.offset.0 + x as i32, self.offset.1 + y as i32), cell))
+            })
+        })
+    }
+
+    pub fn contains(&self, cell: (usize, usize)) -> bool {
+        let (x, y) = (cell.0 as i32 - self.offset.0, cell.1 as i32 - self.offset.1);
+        x >= 0
+            && y >= 0
+            && self
+                .clip
+                .mask
+                .get(x as usize)
+                .and_then(|col| col.get(y as usize))
+                .copied()
+                .unwrap_or(false)
+    }
+
+    pub fn stamp(&self, matrix: &mut Frame) {
+        let (width, height) = matrix.size();
+        for ((x, y), cell) in self.cells() {
+            if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                matrix.set(x as usize, y as usize, Some(cell));
+            }
+        }
+    }
+}
+
+/// Outlines the border of the cells for which `selected(x, y)` is true
+fn paint_outline(
+    painter: &Painter,
+    panel: &Panel,
+    size: (i32, i32),
+    offset: (i32, i32),
+    selected: impl Fn(i32, i32) -> bool,
+    color: Color32,
+) {
+    let stroke = Stroke::new(1.5, color);
+    let corner = |x: i32, y: i32| panel.cell_pos(x + offset.0, y + offset.1);
+    let line = |a: Pos2, b: Pos2| painter.line_segment([a, b], stroke);
+    for x in 0..size.0 {
+        for y in 0..size.1 {
+            if !selected(x, y) {
+                continue;
+            }
+            if !selected(x - 1, y) {
+                line(corner(x, y), corner(x, y + 1));
+            }
+            if !selected(x + 1, y) {
+                line(corner(x + 1, y), corner(x + 1, y + 1));
+            }
+            if !selected(x, y - 1) {
+                line(corner(x, y), corner(x + 1, y));
+            }
+            if !selected(x, y + 1) {
+                line(corner(x, y + 1), corner(x + 1, y + 1));
+            }
+        }
+    }
+}
+
+impl TemplateApp {
+    pub(super) fn copy_selection(&mut self, ctx: &egui::Context) {
+        let selection = self
+            .selection
+            .clone()
+            .unwrap_or_else(|| Selection::all(self.frame_size()));
+        if let Some(clip) = Clip::from_selection(&self.ref_matrix[self.current_frame], &selection) {
+            let text = clip.to_text();
+            ctx.copy_text(text.clone());
+            self.clipboard = Some(text);
+        }
+    }
+
+    pub(super) fn cut_selection(&mut self, ctx: &egui::Context) {
+        self.copy_selection(ctx);
+        self.delete_selection();
+    }
+
+    pub(super) fn delete_selection(&mut self) {
+        let selection = self
+            .selection
+            .clone()
+            .unwrap_or_else(|| Selection::all(self.frame_size()));
+        let frame = &mut self.ref_matrix[self.current_frame];
+        let (width, height) = frame.size();
+        for x in 0..width {
+            for y in 0..height {
+                if selection.contains(x, y) {
+                    frame.set(x, y, None);
+                }
+            }
+        }
+    }
+
+    /// Pastes clipboard text as a floating clip, returns false if it isn't a copied region
+    pub(super) fn paste(&mut self, text: &str) -> bool {
+        match Clip::from_text(text) {
+            Some(clip) => {
+                self.commit_floating();
+                self.floating = Some(Floating::new(clip));
+                self.selection = None;
+                if !matches!(self.tool, Tool::Select | Tool::Lasso) {
+                    self.tool = Tool::Select;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stamps the floating clip onto the current frame and selects what was pasted
+    pub(super) fn commit_floating(&mut self) {
+        if let Some(floating) = self.floating.take() {
+            let size = self.frame_size();
+            floating.stamp(&mut self.ref_matrix[self.current_frame]);
+            let mut mask = vec![vec![false; size.1]; size.0];
+            for (x, col) in mask.iter_mut().enumerate() {
+                for (y, cell) in col.iter_mut().enumerate() {
+                    *cell = floating.contains((x, y));
+                }
+            }
+            self.selection = Some(Selection { mask });
+        }
+    }
+
+    /// Clipboard shortcuts, marquee/lasso dragging and moving the floating clip
+    pub(super) fn update_selection(&mut self, ctx: &egui::Context, painter: &Painter) {
+        if !ctx.wants_keyboard_input() {
+            for event in ctx.input(|i| i.events.clone()) {
+                match event {
+                    egui::Event::Copy => self.copy_selection(ctx),
+                    egui::Event::Cut => self.cut_selection(ctx),
+                    egui::Event::Paste(text) => {
+                        self.paste(&text);
+                    }
+                    _ => {}
+                }
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::Enter)) {
+                self.commit_floating();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.floating = None;
+                self.selection = None;
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::Delete)) && self.selection.is_some() {
+                self.delete_selection();
+            }
+        }
+
+        let size = self.frame_size();
+        let pointer = ctx.input(|i| i.pointer.latest_pos());
+        let hovered_cell = pointer.and_then(|pos| self.layout.canvas_cell_at(ctx, pos, size));
+        if matches!(self.tool, Tool::Select | Tool::Lasso) {
+            if ctx.input(|i| i.pointer.primary_pressed()) {
+                if let Some(cell) = hovered_cell {
+                    match &self.floating {
+                        Some(floating) if floating.contains(cell) => {
+                            self.float_grab = Some((
+                                cell.0 as i32 - floating.offset.0,
+                                cell.1 as i32 - floating.offset.1,
+                            ));
+                        }
+                        _ => {
+                            self.commit_floating();
+                            self.selection = None;
+                            self.tool_anchor = Some(cell);
+                            self.tool_corner = Some(cell);
+                            self.lasso_path = vec![cell];
+                        }
+                    }
+                }
+            }
+            if let Some(cell) = hovered_cell {
+                if let (Some(grab), Some(floating)) = (self.float_grab, self.floating.as_mut()) {
+                    floating.offset = (cell.0 as i32 - grab.0, cell.1 as i32 - grab.1);
+                } else if self.tool_anchor.is_some() {
+                    self.tool_corner = Some(cell);
+                    if self.lasso_path.last() != Some(&cell) {
+                        self.lasso_path.push(cell);
+                    }
+                }
+            }
+            if ctx.input(|i| i.pointer.primary_released()) {
+                if let (Some(anchor), Some(corner)) = (self.tool_anchor, self.tool_corner) {
+                    self.selection = Some(match self.tool {
+                        Tool::Lasso => Selection::lasso(size, &self.lasso_path),
+                        _ => Selection::rect(size, anchor, corner),
+                    });
+                }
+                self.tool_anchor = None;
+                self.tool_corner = None;
+                self.lasso_path.clear();
+                self.float_grab = None;
+            }
+        }
+
+        //# Drawing
+        let frame_size = (size.0 as i32, size.1 as i32);
+        if let Some(floating) = &self.floating {
+            for ((x, y), (rx, ry)) in floating.cells() {
+                if x < 0 || y < 0 || x >= frame_size.0 || y >= frame_size.1 {
+                    continue;
+                }
+                if let Some(color) = self.ref_color((rx, ry)) {
+                    painter.rect_filled(
+                        self.layout.canvas.cell_rect(x as usize, y as usize),
+                        0.0,
+                        color,
+                    );
+                }
+            }
+            let clip_size = floating.clip.size();
+            paint_outline(
+                painter,
+                &self.layout.canvas,
+                (clip_size.0 as i32, clip_size.1 as i32),
+                floating.offset,
+                |x, y| {
+                    x >= 0
+                        && y >= 0
+                        && floating
+                            .clip
+                            .mask
+                            .get(x as usize)
+                            .and_then(|col| col.get(y as usize))
+                            .copied()
+                            .unwrap_or(false)
+                },
+                Color32::LIGHT_BLUE,
+            );
+        }
+        if let Some(selection) = &self.selection {
+            paint_outline(
+                painter,
+                &self.layout.canvas,
+                frame_size,
+                (0, 0),
+                |x, y| x >= 0 && y >= 0 && selection.contains(x as usize, y as usize),
+                Color32::YELLOW,
+            );
+        }
+        if let (Some(anchor), Some(corner)) = (self.tool_anchor, self.tool_corner) {
+            if matches!(self.tool, Tool::Select | Tool::Lasso) {
+                let preview = match self.tool {
+                    Tool::Lasso => Selection::lasso(size, &self.lasso_path),
+                    _ => Selection::rect(size, anchor, corner),
+                };
+                paint_outline(
+                    painter,
+                    &self.layout.canvas,
+                    frame_size,
+                    (0, 0),
+                    |x, y| x >= 0 && y >= 0 && preview.contains(x as usize, y as usize),
+                    Color32::YELLOW,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clips_round_trip_through_text() {
+        let mut frame = Frame::new((4, 4));
+        frame.set(1, 2, Some((3, 4)));
+        let selection = Selection::rect((4, 4), (1, 1), (2, 2));
+        let clip = Clip::from_selection(&frame, &selection).unwrap();
+        let pasted = Clip::from_text(&format!("  {}\n", clip.to_text())).unwrap();
+        assert_eq!(pasted.origin, (1, 1));
+        assert_eq!(pasted.size(), (2, 2));
+        assert_eq!(pasted.refs, clip.refs);
+        assert_eq!(pasted.refs[0][1], Some((3, 4)));
+    }
+
+    #[test]
+    fn copying_over_a_smaller_frame_keeps_what_fits() {
+        let mut frame = Frame::new((2, 2));
+        frame.set(1, 1, Some((7, 7)));
+        // Made on a 4x4 frame before switching to this one
+        let selection = Selection::rect((4, 4), (1, 1), (3, 3));
+        let clip = Clip::from_selection(&frame, &selection).unwrap();
+        assert_eq!(clip.origin, (1, 1));
+        assert_eq!(clip.size(), (1, 1));
+        assert_eq!(clip.refs, [[Some((7, 7))]]);
+        assert!(Clip::from_selection(&frame, &Selection::rect((4, 4), (2, 2), (3, 3))).is_none());
+    }
+
+    #[test]
+    fn clip_text_rejects_other_and_malformed_clips() {
+        assert!(Clip::from_text("hello").is_none());
+        assert!(Clip::from_text(r#"{"origin":[0,0],"mask":[[true]],"refs":[[null]]}"#).is_none());
+        assert!(Clip::from_text("pxref-clip:{").is_none());
+        assert!(Clip::from_text(r#"pxref-clip:{"origin":[0,0],"mask":[],"refs":[]}"#).is_none());
+        let jagged = r#"pxref-clip:{"origin":[0,0],"mask":[[true,true],[true]],"refs":[[null,null],[null]]}"#;
+        assert!(Clip::from_text(jagged).is_none());
+        let mismatched = r#"pxref-clip:{"origin":[0,0],"mask":[[true]],"refs":[[null],[null]]}"#;
+        assert!(Clip::from_text(mismatched).is_none());
+        assert!(
+            Clip::from_text(r#"pxref-clip:{"origin":[0,0],"mask":[[true]],"refs":[[[1,2]]]}"#)
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use super::{TemplateApp, Tool};
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+/// Everything that can be bound to a keyboard shortcut
+#[derive(Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Action {
+    SaveRef,
+    SaveRefAs,
+    LoadRef,
+    Undo,
+    Redo,
+    NextFrame,
+    PreviousFrame,
+    TogglePlay,
+    ShadeLighter,
+    ShadeDarker,
+    SelectTool(Tool),
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::SaveRef,
+        Action::SaveRefAs,
+        Action::LoadRef,
+        Action::Undo,
+        Action::Redo,
+        Action::NextFrame,
+        Action::PreviousFrame,
+        Action::TogglePlay,
+        Action::ShadeLighter,
+        Action::ShadeDarker,
+        Action::SelectTool(Tool::Drag),
+        Action::SelectTool(Tool::Bucket),
+        Action::SelectTool(Tool::Rectangle),
+        Action::SelectTool(Tool::Select),
+        Action::SelectTool(Tool::Lasso),
+        Action::SelectTool(Tool::Shade),
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            Action::SaveRef => "Save Ref".to_string(),
+            Action::SaveRefAs => "Save Ref As".to_string(),
+            Action::LoadRef => "Load Ref".to_string(),
+            Action::Undo => "Undo".to_string(),
+            Action::Redo => "Redo".to_string(),
+            Action::NextFrame => "Next frame".to_string(),
+            Action::PreviousFrame => "Previous frame".to_string(),
+            Action::TogglePlay => "Play/Pause".to_string(),
+            Action::ShadeLighter => "Shade lighter".to_string(),
+            Action::ShadeDarker => "Shade darker".to_string(),
+            Action::SelectTool(tool) => format!("{} tool", tool.name()),
+        }
+    }
+
+    pub fn default_shortcut(&self) -> Option<KeyboardShortcut> {
+        let (modifiers, key) = match self {
+            Action::SaveRef => (Modifiers::COMMAND, Key::S),
+            Action::SaveRefAs => (Modifiers::COMMAND | Modifiers::SHIFT, Key::S),
+            Action::LoadRef => (Modifiers::COMMAND, Key::O),
+            Action::Undo => (Modifiers::COMMAND, Key::Z),
+            Action::Redo => (Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+            Action::NextFrame => (Modifiers::NONE, Key::Period),
+            Action::PreviousFrame => (Modifiers::NONE, Key::Comma),
+            Action::TogglePlay => (Modifiers::NONE, Key::Space),
+            Action::ShadeLighter => (Modifiers::NONE, Key::CloseBracket),
+            Action::ShadeDarker => (Modifiers::NONE, Key::OpenBracket),
+            Action::SelectTool(Tool::Drag) => (Modifiers::NONE, Key::D),
+            Action::SelectTool(Tool::Bucket) => (Modifiers::NONE, Key::B),
+            Action::SelectTool(Tool::Rectangle) => (Modifiers::NONE, Key::R),
+            Action::SelectTool(Tool::Select) => (Modifiers::NONE, Key::M),
+            Action::SelectTool(Tool::Lasso) => (Modifiers::NONE, Key::L),
+            Action::SelectTool(Tool::Shade) => (Modifiers::NONE, Key::H),
+        };
+        Some(KeyboardShortcut::new(modifiers, key))
+    }
+}
+
+/// User bindings, actions not listed here use their default shortcut
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Keymap {
+    bindings: Vec<(Action, Option<KeyboardShortcut>)>,
+}
+
+impl Keymap {
+    pub fn get(&self, action: Action) -> Option<KeyboardShortcut> {
+        match self.bindings.iter().find(|(a, _)| *a == action) {
+            Some((_, shortcut)) => *shortcut,
+            None => action.default_shortcut(),
+        }
+    }
+
+    /// Binds `action`, unbinding any other action using the same shortcut
+    pub fn set(&mut self, action: Action, shortcut: Option<KeyboardShortcut>) {
+        if shortcut.is_some() {
+            for other in Action::ALL {
+                if other != action && self.get(other) == shortcut {
+                    self.set(other, None);
+                }
+            }
+        }
+        self.bindings.retain(|(a, _)| *a != action);
+        self.bindings.push((action, shortcut));
+    }
+
+    /// Bound actions, the ones with more modifiers first so Ctrl+Shift+Z isn't taken by Ctrl+Z
+    fn sorted(&self) -> Vec<(Action, KeyboardShortcut)> {
+        let mut bound: Vec<_> = Action::ALL
+            .iter()
+            .filter_map(|action| self.get(*action).map(|shortcut| (*action, shortcut)))
+            .collect();
+        bound.sort_by_key(|(_, shortcut)| {
+            let m = shortcut.modifiers;
+            std::cmp::Reverse(
+                m.alt as u8 + m.shift as u8 + (m.ctrl || m.command || m.mac_cmd) as u8,
+            )
+        });
+        bound
+    }
+}
+
+impl TemplateApp {
+    /// Menu label hint for an action's shortcut
+    pub(super) fn shortcut_text(&self, ctx: &egui::Context, action: Action) -> String {
+        self.keymap
+            .get(action)
+            .map_or(String::new(), |shortcut| ctx.format_shortcut(&shortcut))
+    }
+
+    pub(super) fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        let frames_len = self.ref_matrix.len();
+        match action {
+            Action::SaveRef => {
+                self.save_ref();
+            }
+            Action::SaveRefAs => {
+                self.save_ref_as();
+            }
+            Action::LoadRef => self.load_ref(ctx),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::NextFrame => self.current_frame = (self.current_frame + 1) % frames_len,
+            Action::PreviousFrame => {
+                self.current_frame = (self.current_frame + frames_len - 1) % frames_len
+            }
+            Action::TogglePlay => self.toggle_playback(),
+            Action::ShadeLighter => self.shade_selection(1),
+            Action::ShadeDarker => self.shade_selection(-1),
+            Action::SelectTool(tool) => self.tool = tool,
+        }
+    }
+
+    /// Runs the actions whose shortcut was pressed, unless a text field has focus
+    pub(super) fn update_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() || self.rebinding.is_some() || self.confirm.is_some() {
+            return;
+        }
+        for (action, shortcut) in self.keymap.sorted() {
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.run_action(ctx, action);
+            }
+        }
+    }
+
+    /// Keymap editor: click a shortcut then press the new key combination, Escape cancels
+    pub(super) fn keymap_window(&mut self, ctx: &egui::Context) {
+        if let Some(action) = self.rebinding {
+            // Taken out of the input so it doesn't also press the focused button
+            let pressed = ctx.input_mut(|i| {
+                let k = i
+                    .events
+                    .iter()
+                    .position(|event| matches!(event, egui::Event::Key { pressed: true, .. }))?;
+                match i.events.remove(k) {
+                    egui::Event::Key { key, modifiers, .. } => {
+                        Some(KeyboardShortcut::new(modifiers, key))
+                    }
+                    _ => None,
+                }
+            });
+            if let Some(shortcut) = pressed {
+                if shortcut.logical_key != Key::Escape {
+                    self.keymap.set(action, Some(shortcut));
+                }
+                self.rebinding = None;
+            }
+        }
+
+        let mut open = self.show_keymap;
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("keymap").striped(true).show(ui, |ui| {
+                    for action in Action::ALL {
+                        ui.label(action.name());
+                        let text = if self.rebinding == Some(action) {
+                            "Press keys...".to_string()
+                        } else {
+                            self.keymap
+                                .get(action)
+                                .map_or("None".to_string(), |shortcut| {
+                                    ctx.format_shortcut(&shortcut)
+                                })
+                        };
+                        if ui.button(text).clicked() {
+                            self.rebinding = Some(action);
+                        }
+                        if ui
+                            .add_enabled(
+                                self.keymap.get(action).is_some(),
+                                egui::Button::new("Clear"),
+                            )
+                            .clicked()
+                        {
+                            self.keymap.set(action, None);
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                if ui.button("Reset to defaults").clicked() {
+                    self.keymap = Keymap::default();
+                }
+            });
+        if !open {
+            self.rebinding = None;
+        }
+        self.show_keymap = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(bound: &[(Action, KeyboardShortcut)], action: Action) -> Option<usize> {
+        bound.iter().position(|(a, _)| *a == action)
+    }
+
+    #[test]
+    fn shortcuts_with_more_modifiers_come_first() {
+        let bound = Keymap::default().sorted();
+        assert_eq!(bound.len(), Action::ALL.len());
+        assert!(position(&bound, Action::Redo) < position(&bound, Action::Undo));
+        assert!(position(&bound, Action::SaveRefAs) < position(&bound, Action::SaveRef));
+        assert!(position(&bound, Action::Undo) < position(&bound, Action::TogglePlay));
+    }
+
+    #[test]
+    fn unbound_and_taken_over_actions_are_left_out() {
+        let mut keymap = Keymap::default();
+        keymap.set(Action::TogglePlay, None);
+        keymap.set(Action::NextFrame, Action::Undo.default_shortcut());
+        let bound = keymap.sorted();
+        assert_eq!(position(&bound, Action::TogglePlay), None);
+        assert_eq!(position(&bound, Action::Undo), None);
+        assert!(position(&bound, Action::NextFrame) < position(&bound, Action::PreviousFrame));
+    }
+}
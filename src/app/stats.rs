@@ -0,0 +1,173 @@
+use super::{ColorMatrix, Frame, TemplateApp};
+use egui::{Color32, Painter};
+
+/// How many canvas cells across all frames point at each reference pixel (indexed `[x][y]`)
+pub fn usage_counts(frames: &[Frame], reference_size: (usize, usize)) -> Vec<Vec<usize>> {
+    let mut counts = vec![vec![0; reference_size.1]; reference_size.0];
+    for (x, y) in frames.iter().flat_map(Frame::refs) {
+        if let Some(count) = counts.get_mut(x).and_then(|col| col.get_mut(y)) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+pub struct FrameStats {
+    pub unique_refs: usize,
+    pub filled_cells: usize,
+    /// Cells whose ref points at a transparent reference pixel
+    pub transparent_cells: Vec<(usize, usize)>,
+}
+
+pub fn frame_stats(frame: &Frame, color_matrix: &ColorMatrix) -> FrameStats {
+    let mut refs: Vec<(usize, usize)> = Vec::new();
+    let mut filled_cells = 0;
+    let mut transparent_cells = Vec::new();
+    for ((x, y), cell) in frame.cells() {
+        let Some(pos) = cell else { continue };
+        filled_cells += 1;
+        if !refs.contains(&pos) {
+            refs.push(pos);
+        }
+        if let Some(None) = color_matrix.get(pos.0).and_then(|col| col.get(pos.1)) {
+            transparent_cells.push((x, y));
+        }
+    }
+    FrameStats {
+        unique_refs: refs.len(),
+        filled_cells,
+        transparent_cells,
+    }
+}
+
+/// Opaque reference pixels no frame uses
+pub fn unused_refs(counts: &[Vec<usize>], color_matrix: &ColorMatrix) -> Vec<(usize, usize)> {
+    let mut unused = Vec::new();
+    for (x, col) in color_matrix.iter().enumerate() {
+        for (y, color) in col.iter().enumerate() {
+            if color.is_some() && counts[x][y] == 0 {
+                unused.push((x, y));
+            }
+        }
+    }
+    unused
+}
+
+/// Blue for rarely used pixels through red for the most used one
+fn heat_color(count: usize, max: usize) -> Color32 {
+    let t = count as f32 / max.max(1) as f32;
+    Color32::from_rgba_unmultiplied((255. * t) as u8, 0, (255. * (1. - t)) as u8, 170)
+}
+
+impl TemplateApp {
+    /// Colors every used reference pixel by how often it's used, and crosses out unused opaque ones
+    pub(super) fn paint_heatmap(&self, painter: &Painter) {
+        let counts = usage_counts(&self.ref_matrix, self.reference_size());
+        let max = counts.iter().flatten().copied().max().unwrap_or(0);
+        for (x, col) in counts.iter().enumerate() {
+            for (y, count) in col.iter().enumerate() {
+                let rect = self.layout.reference.cell_rect(x, y);
+                if *count > 0 {
+                    painter.rect_filled(rect, 0.0, heat_color(*count, max));
+                } else if self.color_matrix[x][y].is_some() {
+                    let stroke = egui::Stroke::new(1.0, Color32::from_black_alpha(160));
+                    painter.line_segment([rect.left_top(), rect.right_bottom()], stroke);
+                    painter.line_segment([rect.right_top(), rect.left_bottom()], stroke);
+                }
+            }
+        }
+    }
+
+    pub(super) fn stats_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_stats;
+        egui::Window::new("Statistics")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let counts = usage_counts(&self.ref_matrix, self.reference_size());
+                let unused = unused_refs(&counts, &self.color_matrix);
+                let stats: Vec<FrameStats> = self
+                    .ref_matrix
+                    .iter()
+                    .map(|frame| frame_stats(frame, &self.color_matrix))
+                    .collect();
+
+                egui::Grid::new("frame_stats").striped(true).show(ui, |ui| {
+                    ui.strong("Frame");
+                    ui.strong("Unique refs");
+                    ui.strong("Filled cells");
+                    ui.strong("Transparent refs");
+                    ui.end_row();
+                    for (j, frame) in stats.iter().enumerate() {
+                        ui.label(format!("{}", j + 1));
+                        ui.label(frame.unique_refs.to_string());
+                        ui.label(frame.filled_cells.to_string());
+                        ui.label(frame.transparent_cells.len().to_string());
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.label(format!(
+                    "Unused reference pixels: {} of {}",
+                    unused.len(),
+                    self.color_matrix
+                        .iter()
+                        .flatten()
+                        .filter(|color| color.is_some())
+                        .count()
+                ));
+                egui::ScrollArea::vertical()
+                    .max_height(200.)
+                    .show(ui, |ui| {
+                        egui::CollapsingHeader::new("Unused pixels").show(ui, |ui| {
+                            for (x, y) in &unused {
+                                ui.label(format!("({}, {})", x, y));
+                            }
+                        });
+                        egui::CollapsingHeader::new("Cells pointing at transparent pixels").show(
+                            ui,
+                            |ui| {
+                                for (j, frame) in stats.iter().enumerate() {
+                                    for (x, y) in &frame.transparent_cells {
+                                        if ui
+                                            .link(format!("Frame {}: cell ({}, {})", j + 1, x, y))
+                                            .clicked()
+                                        {
+                                            self.current_frame = j;
+                                        }
+                                    }
+                                }
+                            },
+                        );
+                    });
+            });
+        self.show_stats = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_is_counted_across_frames() {
+        let mut first = Frame::new((2, 2));
+        first.set(0, 0, Some((0, 0)));
+        first.set(1, 0, Some((0, 0)));
+        first.set(1, 1, Some((1, 1)));
+        let mut second = Frame::new((2, 2));
+        second.set(0, 1, Some((0, 0)));
+        // Out of the reference: ignored
+        second.set(1, 1, Some((5, 5)));
+
+        let counts = usage_counts(&[first, second], (2, 2));
+        assert_eq!(counts, vec![vec![3, 0], vec![0, 1]]);
+    }
+
+    #[test]
+    fn unused_refs_skip_transparent_pixels() {
+        let opaque = Some(Color32::WHITE);
+        let color_matrix = vec![vec![opaque, None], vec![opaque, opaque]];
+        let counts = vec![vec![3, 0], vec![0, 1]];
+        assert_eq!(unused_refs(&counts, &color_matrix), vec![(1, 0)]);
+    }
+}
@@ -0,0 +1,202 @@
+use super::{fill_rect, flood_fill, remap_ref, Panel, TemplateApp};
+use egui::{vec2, Color32, Painter, Stroke};
+
+/// Mirror axes applied when placing or erasing refs on the canvas
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// Left and right halves mirror each other
+    Vertical,
+    /// Top and bottom halves mirror each other
+    Horizontal,
+    Both,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 4] = [
+        Symmetry::None,
+        Symmetry::Vertical,
+        Symmetry::Horizontal,
+        Symmetry::Both,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Symmetry::None => "None",
+            Symmetry::Vertical => "Vertical Axis",
+            Symmetry::Horizontal => "Horizontal Axis",
+            Symmetry::Both => "Both Axes",
+        }
+    }
+
+    /// `cell` followed by its mirrored counterparts, each flagged if it was flipped left to right.
+    /// Always the same count and order for a given mode, even if some of them coincide.
+    fn mirrors(&self, cell: (usize, usize), size: (usize, usize)) -> Vec<((usize, usize), bool)> {
+        let flip_x = (size.0 - 1 - cell.0, cell.1);
+        let flip_y = (cell.0, size.1 - 1 - cell.1);
+        let flip_xy = (flip_x.0, flip_y.1);
+        match self {
+            Symmetry::None => vec![(cell, false)],
+            Symmetry::Vertical => vec![(cell, false), (flip_x, true)],
+            Symmetry::Horizontal => vec![(cell, false), (flip_y, false)],
+            Symmetry::Both => vec![
+                (cell, false),
+                (flip_x, true),
+                (flip_y, false),
+                (flip_xy, true),
+            ],
+        }
+    }
+
+    /// Like `mirrors` without the cells that coincide
+    pub fn cells(&self, cell: (usize, usize), size: (usize, usize)) -> Vec<((usize, usize), bool)> {
+        let candidates = self.mirrors(cell, size);
+        let mut cells: Vec<((usize, usize), bool)> = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            if !cells.iter().any(|(pos, _)| *pos == candidate.0) {
+                cells.push(candidate);
+            }
+        }
+        cells
+    }
+
+    pub fn paint_axes(&self, painter: &Painter, panel: &Panel, size: (usize, usize)) {
+        let stroke = Stroke::new(1.0, Color32::from_rgba_unmultiplied(255, 80, 80, 160));
+        let origin = panel.cell_pos(0, 0);
+        let (width, height) = (
+            size.0 as f32 * panel.cell_size(),
+            size.1 as f32 * panel.cell_size(),
+        );
+        if matches!(self, Symmetry::Vertical | Symmetry::Both) {
+            painter.line_segment(
+                [
+                    origin + vec2(width / 2., 0.),
+                    origin + vec2(width / 2., height),
+                ],
+                stroke,
+            );
+        }
+        if matches!(self, Symmetry::Horizontal | Symmetry::Both) {
+            painter.line_segment(
+                [
+                    origin + vec2(0., height / 2.),
+                    origin + vec2(width, height / 2.),
+                ],
+                stroke,
+            );
+        }
+    }
+}
+
+impl TemplateApp {
+    /// Ref the counterpart cell gets, swapping paired regions on left/right flips if enabled
+    fn counterpart(
+        &self,
+        value: Option<(usize, usize)>,
+        flipped_x: bool,
+    ) -> Option<(usize, usize)> {
+        if flipped_x && self.symmetry_remap {
+            value.map(|pos| remap_ref(&self.remap_pairs, pos))
+        } else {
+            value
+        }
+    }
+
+    /// Places (or erases with `None`) a ref on the current frame, honoring the symmetry mode
+    pub(super) fn place_ref(&mut self, cell: (usize, usize), value: Option<(usize, usize)>) {
+        let size = self.frame_size();
+        for (target, flipped_x) in self.symmetry.cells(cell, size) {
+            let value = self.counterpart(value, flipped_x);
+            self.ref_matrix[self.current_frame].set(target.0, target.1, value);
+        }
+    }
+
+    /// Bucket fill honoring the symmetry mode. A mirrored region that the first fill already
+    /// reached isn't filled again.
+    pub(super) fn place_flood_fill(&mut self, cell: (usize, usize), value: Option<(usize, usize)>) {
+        let size = self.frame_size();
+        let before = self.ref_matrix[self.current_frame].clone();
+        for (target, flipped_x) in self.symmetry.cells(cell, size) {
+            let frame = &self.ref_matrix[self.current_frame];
+            if target != cell && frame.get(target.0, target.1) != before.get(target.0, target.1) {
+                continue;
+            }
+            let value = self.counterpart(value, flipped_x);
+            flood_fill(&mut self.ref_matrix[self.current_frame], target, value);
+        }
+    }
+
+    /// Rectangle fill honoring the symmetry mode
+    pub(super) fn place_rect(
+        &mut self,
+        a: (usize, usize),
+        b: (usize, usize),
+        value: Option<(usize, usize)>,
+    ) {
+        let size = self.frame_size();
+        let corners_a = self.symmetry.mirrors(a, size);
+        let corners_b = self.symmetry.mirrors(b, size);
+        for ((a, flipped_x), (b, _)) in corners_a.into_iter().zip(corners_b) {
+            let value = self.counterpart(value, flipped_x);
+            fill_rect(&mut self.ref_matrix[self.current_frame], a, b, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_axis_mirrors_across_the_middle() {
+        let size = (4, 3);
+        assert_eq!(Symmetry::None.cells((0, 0), size), vec![((0, 0), false)]);
+        assert_eq!(
+            Symmetry::Vertical.cells((1, 0), size),
+            vec![((1, 0), false), ((2, 0), true)]
+        );
+        assert_eq!(
+            Symmetry::Horizontal.cells((1, 0), size),
+            vec![((1, 0), false), ((1, 2), false)]
+        );
+        assert_eq!(
+            Symmetry::Both.cells((0, 0), size),
+            vec![
+                ((0, 0), false),
+                ((3, 0), true),
+                ((0, 2), false),
+                ((3, 2), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn cells_on_the_axis_are_not_repeated() {
+        // Odd width and height: the middle column and row are their own mirrors
+        let size = (5, 3);
+        assert_eq!(
+            Symmetry::Vertical.cells((2, 0), size),
+            vec![((2, 0), false)]
+        );
+        assert_eq!(
+            Symmetry::Vertical.cells((0, 1), size),
+            vec![((0, 1), false), ((4, 1), true)]
+        );
+        assert_eq!(
+            Symmetry::Horizontal.cells((0, 1), size),
+            vec![((0, 1), false)]
+        );
+        assert_eq!(Symmetry::Both.cells((2, 1), size), vec![((2, 1), false)]);
+        assert_eq!(
+            Symmetry::Both.cells((2, 0), size),
+            vec![((2, 0), false), ((2, 2), false)]
+        );
+
+        // Even width: no column is its own mirror
+        assert_eq!(
+            Symmetry::Vertical.cells((2, 0), (4, 1)),
+            vec![((2, 0), false), ((1, 0), true)]
+        );
+    }
+}
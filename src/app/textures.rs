@@ -0,0 +1,57 @@
+use egui::{Color32, ColorImage, Rect, TextureHandle, TextureOptions};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// A grid drawn as a texture with one texel per cell. The pixels are only rebuilt and uploaded
+/// when the key they were drawn for changes.
+#[derive(Default)]
+pub struct GridTexture {
+    texture: Option<TextureHandle>,
+    key: u64,
+    size: [usize; 2],
+}
+
+impl GridTexture {
+    /// Brings the texture up to date with `color(x, y)` for a `size` grid. `key` must change
+    /// whenever any color would, e.g. the frame's version and the colors revision.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        name: &str,
+        key: impl Hash,
+        size: (usize, usize),
+        color: impl Fn(usize, usize) -> Color32,
+    ) {
+        let size = [size.0, size.1];
+        if size[0] == 0 || size[1] == 0 {
+            // Nothing to draw, and empty textures aren't allowed
+            self.texture = None;
+            return;
+        }
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let key = hasher.finish();
+        if self.texture.is_some() && size == self.size && key == self.key {
+            return;
+        }
+        // Row-major, as egui expects, while the matrices are indexed [x][y]
+        let pixels: Vec<Color32> = (0..size[1])
+            .flat_map(|y| (0..size[0]).map(move |x| (x, y)))
+            .map(|(x, y)| color(x, y))
+            .collect();
+        let image = ColorImage { size, pixels };
+        match &mut self.texture {
+            Some(texture) => texture.set(image, TextureOptions::NEAREST),
+            None => self.texture = Some(ctx.load_texture(name, image, TextureOptions::NEAREST)),
+        }
+        self.key = key;
+        self.size = size;
+    }
+
+    /// Draws the grid stretched over `rect`
+    pub fn paint(&self, painter: &egui::Painter, rect: Rect) {
+        if let Some(texture) = &self.texture {
+            let uv = Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.));
+            painter.image(texture.id(), rect, uv, Color32::WHITE);
+        }
+    }
+}
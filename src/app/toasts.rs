@@ -0,0 +1,145 @@
+use super::TemplateApp;
+use egui::{Color32, RichText};
+use web_time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+impl Level {
+    /// Errors stay up longer so there's time to read them
+    fn duration(&self) -> Duration {
+        match self {
+            Level::Info => Duration::from_secs(3),
+            Level::Error => Duration::from_secs(8),
+        }
+    }
+
+    fn color(&self) -> Color32 {
+        match self {
+            Level::Info => Color32::LIGHT_BLUE,
+            Level::Error => Color32::LIGHT_RED,
+        }
+    }
+}
+
+/// Short message shown in a corner until it expires or is clicked away
+pub struct Toast {
+    pub level: Level,
+    pub title: String,
+    pub message: String,
+    pub shown_at: Instant,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    pub list: Vec<Toast>,
+}
+
+impl Toasts {
+    /// Most toasts kept at once, older ones are dropped
+    const MAX: usize = 5;
+
+    pub fn push(&mut self, level: Level, title: &str, message: &str, now: Instant) {
+        self.list.push(Toast {
+            level,
+            title: title.to_string(),
+            message: message.to_string(),
+            shown_at: now,
+        });
+        if self.list.len() > Self::MAX {
+            self.list.remove(0);
+        }
+    }
+
+    /// Drops the toasts shown for longer than their level's duration
+    pub fn expire(&mut self, now: Instant) {
+        self.list
+            .retain(|toast| now.duration_since(toast.shown_at) < toast.level.duration());
+    }
+}
+
+impl TemplateApp {
+    pub(super) fn notify(&mut self, level: Level, title: &str, message: &str) {
+        self.toasts.push(level, title, message, Instant::now());
+    }
+
+    /// Stacks the toasts in the bottom right corner, newest at the bottom
+    pub(super) fn show_toasts(&mut self, ctx: &egui::Context) {
+        self.toasts.expire(Instant::now());
+        if self.toasts.list.is_empty() {
+            return;
+        }
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12., -12.))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (k, toast) in self.toasts.list.iter().enumerate() {
+                    let response = egui::Frame::popup(ui.style())
+                        .show(ui, |ui| {
+                            ui.set_max_width(280.);
+                            ui.label(
+                                RichText::new(&toast.title)
+                                    .strong()
+                                    .color(toast.level.color()),
+                            );
+                            if !toast.message.is_empty() {
+                                ui.label(&toast.message);
+                            }
+                        })
+                        .response
+                        .interact(egui::Sense::click());
+                    if response.on_hover_text("Click to dismiss").clicked() {
+                        dismissed = Some(k);
+                    }
+                }
+            });
+        if let Some(k) = dismissed {
+            self.toasts.list.remove(k);
+        }
+        // Keep repainting so toasts disappear on time even without input
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toasts_expire_after_their_level_duration() {
+        let now = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push(Level::Info, "info", "", now);
+        toasts.push(Level::Error, "error", "", now);
+        toasts.expire(now + Duration::from_secs(1));
+        assert_eq!(toasts.list.len(), 2);
+        toasts.expire(now + Duration::from_secs(4));
+        let titles: Vec<&str> = toasts
+            .list
+            .iter()
+            .map(|toast| toast.title.as_str())
+            .collect();
+        assert_eq!(titles, ["error"]);
+        toasts.expire(now + Duration::from_secs(9));
+        assert!(toasts.list.is_empty());
+    }
+
+    #[test]
+    fn oldest_toasts_are_dropped_past_max() {
+        let now = Instant::now();
+        let mut toasts = Toasts::default();
+        for k in 0..Toasts::MAX + 2 {
+            toasts.push(Level::Info, &k.to_string(), "", now);
+        }
+        let titles: Vec<&str> = toasts
+            .list
+            .iter()
+            .map(|toast| toast.title.as_str())
+            .collect();
+        assert_eq!(titles, ["2", "3", "4", "5", "6"]);
+    }
+}
@@ -0,0 +1,152 @@
+use super::Frame;
+
+/// Tool used when clicking or dragging on the canvas (left panel)
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Tool {
+    /// Drag refs from the reference, shift to erase, ctrl/cmd to move
+    #[default]
+    Drag,
+    /// Fills a contiguous region of cells sharing the clicked cell's ref
+    Bucket,
+    /// Fills the rectangle spanned by a drag
+    Rectangle,
+    /// Rectangular marquee selection
+    Select,
+    /// Freeform selection traced with the pointer
+    Lasso,
+    /// Steps refs along their shading ramp, left click lighter and right click darker
+    Shade,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 6] = [
+        Tool::Drag,
+        Tool::Bucket,
+        Tool::Rectangle,
+        Tool::Select,
+        Tool::Lasso,
+        Tool::Shade,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Drag => "Drag",
+            Tool::Bucket => "Bucket",
+            Tool::Rectangle => "Rectangle",
+            Tool::Select => "Select",
+            Tool::Lasso => "Lasso",
+            Tool::Shade => "Shade",
+        }
+    }
+}
+
+/// Bucket fill: every cell 4-connected to `start` holding the same value (empty or the same ref)
+/// gets `new_ref`. Returns true if anything changed.
+pub fn flood_fill(
+    matrix: &mut Frame,
+    start: (usize, usize),
+    new_ref: Option<(usize, usize)>,
+) -> bool {
+    let (width, height) = matrix.size();
+    if start.0 >= width || start.1 >= height {
+        return false;
+    }
+    let target = matrix.get(start.0, start.1);
+    if target == new_ref {
+        return false;
+    }
+
+    let mut stack = vec![start];
+    while let Some((x, y)) = stack.pop() {
+        if matrix.get(x, y) != target {
+            continue;
+        }
+        matrix.set(x, y, new_ref);
+        if x > 0 {
+            stack.push((x - 1, y));
+        }
+        if y > 0 {
+            stack.push((x, y - 1));
+        }
+        if x + 1 < width {
+            stack.push((x + 1, y));
+        }
+        if y + 1 < height {
+            stack.push((x, y + 1));
+        }
+    }
+    true
+}
+
+/// Fills every cell of the rectangle with corners `a` and `b` (inclusive, in any order) with `new_ref`
+pub fn fill_rect(
+    matrix: &mut Frame,
+    a: (usize, usize),
+    b: (usize, usize),
+    new_ref: Option<(usize, usize)>,
+) {
+    let (width, height) = matrix.size();
+    let (min_x, max_x) = (a.0.min(b.0), a.0.max(b.0));
+    let (min_y, max_y) = (a.1.min(b.1), a.1.max(b.1));
+    for x in min_x..(max_x + 1).min(width) {
+        for y in min_y..(max_y + 1).min(height) {
+            matrix.set(x, y, new_ref);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x4 frame with a wall of refs down column 1
+    fn walled() -> Frame {
+        let mut frame = Frame::new((4, 4));
+        for y in 0..4 {
+            frame.set(1, y, Some((0, 0)));
+        }
+        frame
+    }
+
+    #[test]
+    fn flood_fill_stops_at_other_refs() {
+        let mut frame = walled();
+        assert!(flood_fill(&mut frame, (3, 0), Some((5, 5))));
+        for y in 0..4 {
+            assert_eq!(frame.get(0, y), None);
+            assert_eq!(frame.get(1, y), Some((0, 0)));
+            assert_eq!(frame.get(2, y), Some((5, 5)));
+            assert_eq!(frame.get(3, y), Some((5, 5)));
+        }
+    }
+
+    #[test]
+    fn flood_fill_does_not_leak_diagonally() {
+        let mut frame = Frame::new((2, 2));
+        frame.set(1, 0, Some((0, 0)));
+        frame.set(0, 1, Some((0, 0)));
+        assert!(flood_fill(&mut frame, (0, 0), Some((5, 5))));
+        assert_eq!(frame.get(0, 0), Some((5, 5)));
+        assert_eq!(frame.get(1, 1), None);
+    }
+
+    #[test]
+    fn flood_fill_reports_no_change() {
+        let mut frame = walled();
+        assert!(!flood_fill(&mut frame, (1, 0), Some((0, 0))));
+        assert!(!flood_fill(&mut frame, (4, 0), Some((5, 5))));
+        assert!(frame == walled());
+    }
+
+    #[test]
+    fn fill_rect_takes_corners_in_any_order_and_clips() {
+        let mut frame = Frame::new((4, 4));
+        fill_rect(&mut frame, (9, 3), (2, 2), Some((1, 1)));
+        let filled: Vec<(usize, usize)> = frame
+            .cells()
+            .filter(|(_, cell)| cell.is_some())
+            .map(|(pos, _)| pos)
+            .collect();
+        assert_eq!(filled, [(2, 2), (3, 2), (2, 3), (3, 3)]);
+    }
+}
@@ -0,0 +1,249 @@
+use super::{transpose, Clip, Floating, Frame, Selection, TemplateApp};
+
+/// Mirrors left to right (matrices are indexed `[x][y]`)
+pub fn flip_horizontal<T: Clone>(mut matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    matrix.reverse();
+    matrix
+}
+
+/// Mirrors top to bottom
+pub fn flip_vertical<T: Clone>(mut matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    matrix.iter_mut().for_each(|col| col.reverse());
+    matrix
+}
+
+pub fn rotate_clockwise<T: Clone>(matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    flip_horizontal(transpose(matrix))
+}
+
+pub fn rotate_counter_clockwise<T: Clone>(matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    flip_vertical(transpose(matrix))
+}
+
+/// Moves every cell by `(dx, dy)`, cells pushed off one edge come back on the other if `wrap`,
+/// otherwise they're dropped and the uncovered cells get `fill`
+pub fn shift<T: Clone>(matrix: &[Vec<T>], dx: i32, dy: i32, wrap: bool, fill: T) -> Vec<Vec<T>> {
+    let width = matrix.len() as i32;
+    let height = matrix.first().map_or(0, |col| col.len()) as i32;
+    (0..width)
+        .map(|x| {
+            (0..height)
+                .map(|y| {
+                    let (sx, sy) = (x - dx, y - dy);
+                    if wrap {
+                        matrix[sx.rem_euclid(width) as usize][sy.rem_euclid(height) as usize]
+                            .clone()
+                    } else if sx >= 0 && sy >= 0 && sx < width && sy < height {
+                        matrix[sx as usize][sy as usize].clone()
+                    } else {
+                        fill.clone()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+pub enum Transform {
+    FlipHorizontal,
+    FlipVertical,
+    RotateClockwise,
+    RotateCounterClockwise,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 4] = [
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::RotateClockwise,
+        Transform::RotateCounterClockwise,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transform::FlipHorizontal => "Flip Horizontal",
+            Transform::FlipVertical => "Flip Vertical",
+            Transform::RotateClockwise => "Rotate 90° CW",
+            Transform::RotateCounterClockwise => "Rotate 90° CCW",
+        }
+    }
+
+    pub fn apply<T: Clone>(&self, matrix: Vec<Vec<T>>) -> Vec<Vec<T>> {
+        match self {
+            Transform::FlipHorizontal => flip_horizontal(matrix),
+            Transform::FlipVertical => flip_vertical(matrix),
+            Transform::RotateClockwise => rotate_clockwise(matrix),
+            Transform::RotateCounterClockwise => rotate_counter_clockwise(matrix),
+        }
+    }
+}
+
+impl TemplateApp {
+    /// Turns the selection into a floating clip so it can be transformed and moved freely
+    fn lift_selection(&mut self) {
+        if self.floating.is_some() {
+            return;
+        }
+        let frame = &self.ref_matrix[self.current_frame];
+        if let Some(clip) = self
+            .selection
+            .as_ref()
+            .and_then(|selection| Clip::from_selection(frame, selection))
+        {
+            self.delete_selection();
+            self.selection = None;
+            self.floating = Some(Floating::new(clip));
+        }
+    }
+
+    /// Transforms the floating clip or selection if there is one, the whole current frame otherwise
+    pub(super) fn apply_transform(&mut self, transform: Transform) {
+        let size = self.frame_size();
+        let rotate = matches!(
+            transform,
+            Transform::RotateClockwise | Transform::RotateCounterClockwise
+        );
+        if rotate && size.0 != size.1 && self.floating.is_none() && self.selection.is_none() {
+            // Rotating would swap the frame's width and height, so rotate its content within it,
+            // cropping what sticks out, to keep every frame the same size
+            self.selection = Some(Selection::all(size));
+        }
+        self.lift_selection();
+        if let Some(floating) = &mut self.floating {
+            let (width, height) = floating.clip.size();
+            floating.clip.mask = transform.apply(std::mem::take(&mut floating.clip.mask));
+            floating.clip.refs = transform.apply(std::mem::take(&mut floating.clip.refs));
+            // Rotate around the center rather than the top-left corner
+            if matches!(
+                transform,
+                Transform::RotateClockwise | Transform::RotateCounterClockwise
+            ) {
+                floating.offset.0 += (width as i32 - height as i32) / 2;
+                floating.offset.1 += (height as i32 - width as i32) / 2;
+            }
+        } else {
+            let frame = self.ref_matrix[self.current_frame].to_matrix();
+            self.ref_matrix[self.current_frame] = Frame::from(transform.apply(frame));
+        }
+    }
+
+    /// Moves the floating clip, the selected cells or the whole current frame by one step
+    pub(super) fn nudge(&mut self, dx: i32, dy: i32) {
+        if let Some(floating) = &mut self.floating {
+            floating.offset.0 += dx;
+            floating.offset.1 += dy;
+            return;
+        }
+        let frame = self.ref_matrix[self.current_frame].to_matrix();
+        let shifted = shift(&frame, dx, dy, self.nudge_wrap, None);
+        match &self.selection {
+            Some(selection) => {
+                let mask = selection.clipped(self.frame_size()).mask;
+                let moved = shift(&mask, dx, dy, self.nudge_wrap, false);
+                let frame: Vec<Vec<_>> = frame
+                    .iter()
+                    .enumerate()
+                    .map(|(x, col)| {
+                        col.iter()
+                            .enumerate()
+                            .map(|(y, cell)| {
+                                if moved[x][y] {
+                                    shifted[x][y]
+                                } else if selection.contains(x, y) {
+                                    None
+                                } else {
+                                    *cell
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+                self.ref_matrix[self.current_frame] = Frame::from(frame);
+                self.selection = Some(Selection { mask: moved });
+            }
+            None => self.ref_matrix[self.current_frame] = Frame::from(shifted),
+        }
+    }
+
+    /// Arrow keys nudge by one cell
+    pub(super) fn update_transform_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let arrows = [
+            (egui::Key::ArrowLeft, -1, 0),
+            (egui::Key::ArrowRight, 1, 0),
+            (egui::Key::ArrowUp, 0, -1),
+            (egui::Key::ArrowDown, 0, 1),
+        ];
+        for (key, dx, dy) in arrows {
+            if ctx.input(|i| i.key_pressed(key)) {
+                self.nudge(dx, dy);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3 wide and 2 high, each cell holding `x + 10 * y`
+    fn matrix() -> Vec<Vec<i32>> {
+        (0..3)
+            .map(|x| (0..2).map(|y| x + 10 * y).collect())
+            .collect()
+    }
+
+    #[test]
+    fn rotations_swap_width_and_height() {
+        //              clockwise   counter-clockwise
+        //  0  1  2     10  0       2 12
+        // 10 11 12     11  1       1 11
+        //              12  2       0 10
+        assert_eq!(rotate_clockwise(matrix()), [[10, 11, 12], [0, 1, 2]]);
+        assert_eq!(
+            rotate_counter_clockwise(matrix()),
+            [[2, 1, 0], [12, 11, 10]]
+        );
+    }
+
+    #[test]
+    fn four_rotations_are_the_identity() {
+        let mut rotated = matrix();
+        for _ in 0..4 {
+            rotated = rotate_clockwise(rotated);
+        }
+        assert_eq!(rotated, matrix());
+        assert_eq!(
+            rotate_counter_clockwise(rotate_clockwise(matrix())),
+            matrix()
+        );
+    }
+
+    #[test]
+    fn flips_undo_themselves() {
+        assert_eq!(flip_horizontal(matrix()), [[2, 12], [1, 11], [0, 10]]);
+        assert_eq!(flip_vertical(matrix()), [[10, 0], [11, 1], [12, 2]]);
+        assert_eq!(flip_horizontal(flip_horizontal(matrix())), matrix());
+        assert_eq!(flip_vertical(flip_vertical(matrix())), matrix());
+    }
+
+    #[test]
+    fn shift_drops_or_wraps_cells() {
+        let row = vec![vec![1], vec![2], vec![3]];
+        assert_eq!(shift(&row, 1, 0, false, 0), [[0], [1], [2]]);
+        assert_eq!(shift(&row, -1, 0, false, 0), [[2], [3], [0]]);
+        assert_eq!(shift(&row, 1, 0, true, 0), [[3], [1], [2]]);
+        assert_eq!(shift(&row, -1, 0, true, 0), [[2], [3], [1]]);
+        // Past the size: everything is dropped, or wraps around as many times as needed
+        assert_eq!(shift(&row, 5, 0, false, 0), [[0], [0], [0]]);
+        assert_eq!(shift(&row, -7, 0, false, 0), [[0], [0], [0]]);
+        assert_eq!(shift(&row, 4, 0, true, 0), [[3], [1], [2]]);
+        assert_eq!(shift(&row, -7, 0, true, 0), [[2], [3], [1]]);
+        let column = vec![vec![1, 2]];
+        assert_eq!(shift(&column, 0, 1, false, 0), [[0, 1]]);
+        assert_eq!(shift(&column, 0, -3, true, 0), [[2, 1]]);
+    }
+}
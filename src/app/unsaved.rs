@@ -0,0 +1,64 @@
+use super::{Deferred, PxRefFile, TemplateApp};
+
+impl TemplateApp {
+    /// Remembers the current project as the saved one
+    pub(super) fn mark_saved(&mut self) {
+        self.saved = Some(self.project_file());
+    }
+
+    /// True if the open project is the same as `other`, a copy taken earlier. Cheap enough for
+    /// every frame: unedited frames still share their buffer with the copy's, so they compare
+    /// by pointer.
+    pub(super) fn project_matches(&self, other: &PxRefFile) -> bool {
+        other.ref_png == self.file_path.as_deref().unwrap_or_default()
+            && other.ref_matrix == self.ref_matrix
+            && other.remap_pairs == self.remap_pairs
+            && other.palettes == self.palettes
+            && other.ramps == self.ramps
+    }
+
+    /// True if the project differs from its file, or has content but was never saved
+    pub(super) fn is_dirty(&self) -> bool {
+        if self.project_path.is_none() {
+            return !self.is_empty_project();
+        }
+        self.saved
+            .as_ref()
+            .map_or(true, |saved| !self.project_matches(saved))
+    }
+
+    /// Project or reference PNG changes that would be lost on quit
+    pub(super) fn has_unsaved_changes(&self) -> bool {
+        self.is_dirty() || self.reference_modified
+    }
+
+    /// Holds the window open when closing it would lose changes, until the user confirms
+    pub(super) fn update_close_request(&mut self, ctx: &egui::Context) {
+        if !ctx.input(|i| i.viewport().close_requested()) || self.allow_close {
+            return;
+        }
+        if !self.has_unsaved_changes() {
+            self.allow_close = true;
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        self.after_discard(ctx, Deferred::Quit);
+    }
+
+    /// Project name in the title bar, with `*` while there are unsaved changes
+    pub(super) fn update_title(&mut self, ctx: &egui::Context) {
+        let name = self
+            .project_path
+            .as_deref()
+            .and_then(|path| std::path::Path::new(path).file_name())
+            .map_or("Untitled".to_string(), |name| {
+                name.to_string_lossy().to_string()
+            });
+        let marker = if self.has_unsaved_changes() { "*" } else { "" };
+        let title = format!("{}{} - Reference", name, marker);
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+}
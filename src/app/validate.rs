@@ -0,0 +1,182 @@
+use super::{ColorMatrix, Frame, TemplateApp};
+use egui::{Color32, Painter, Stroke};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// The ref points outside the reference image
+    OutOfBounds,
+    /// The ref points at a transparent reference pixel, so it renders as nothing
+    Transparent,
+}
+
+impl ProblemKind {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ProblemKind::OutOfBounds => "outside the reference",
+            ProblemKind::Transparent => "transparent pixel",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Problem {
+    pub frame: usize,
+    pub cell: (usize, usize),
+    pub pos: (usize, usize),
+    pub kind: ProblemKind,
+}
+
+pub fn check_ref(color_matrix: &ColorMatrix, pos: (usize, usize)) -> Option<ProblemKind> {
+    match color_matrix.get(pos.0).and_then(|col| col.get(pos.1)) {
+        None => Some(ProblemKind::OutOfBounds),
+        Some(None) => Some(ProblemKind::Transparent),
+        Some(Some(_)) => None,
+    }
+}
+
+/// Every cell of every frame whose ref is broken, in frame then cell order
+pub fn validate(frames: &[Frame], color_matrix: &ColorMatrix) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (frame, matrix) in frames.iter().enumerate() {
+        for ((x, y), cell) in matrix.cells() {
+            let Some(pos) = cell else { continue };
+            if let Some(kind) = check_ref(color_matrix, pos) {
+                problems.push(Problem {
+                    frame,
+                    cell: (x, y),
+                    pos,
+                    kind,
+                });
+            }
+        }
+    }
+    // Reported by cell column then row, like before frames were stored row by row
+    problems.sort_by_key(|problem| (problem.frame, problem.cell));
+    problems
+}
+
+/// Marks a canvas cell holding a broken ref: a cross when out of bounds, an outline when transparent
+pub fn paint_problem(painter: &Painter, rect: egui::Rect, kind: ProblemKind) {
+    let stroke = Stroke::new(1.5, Color32::RED);
+    match kind {
+        ProblemKind::OutOfBounds => {
+            let rect = rect.shrink(rect.width() / 4.);
+            painter.line_segment([rect.left_top(), rect.right_bottom()], stroke);
+            painter.line_segment([rect.right_top(), rect.left_bottom()], stroke);
+        }
+        ProblemKind::Transparent => {
+            painter.rect_stroke(rect.shrink(1.), 0.0, stroke);
+        }
+    }
+}
+
+impl TemplateApp {
+    /// Re-checks the project, opening the report if anything is broken
+    pub(super) fn run_validation(&mut self) {
+        self.problems = validate(&self.ref_matrix, &self.color_matrix);
+        if !self.problems.is_empty() {
+            self.show_validation = true;
+        }
+    }
+
+    /// Re-checks the project without opening the report, after edits that can add or remove frames
+    pub(super) fn refresh_problems(&mut self) {
+        self.problems = validate(&self.ref_matrix, &self.color_matrix);
+    }
+
+    fn fix_problem(&mut self, problem: Problem, replacement: Option<(usize, usize)>) {
+        let Some(frame) = self.ref_matrix.get_mut(problem.frame) else {
+            return;
+        };
+        let size = frame.size();
+        if problem.cell.0 < size.0 && problem.cell.1 < size.1 {
+            frame.set(problem.cell.0, problem.cell.1, replacement);
+        }
+    }
+
+    /// Lists broken refs by frame and cell, and clears or remaps them to the selected ref
+    pub(super) fn validation_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_validation;
+        let mut fixes: Vec<(Problem, Option<(usize, usize)>)> = Vec::new();
+        egui::Window::new("Validation")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Re-check").clicked() {
+                        self.refresh_problems();
+                    }
+                    if ui
+                        .add_enabled(!self.problems.is_empty(), egui::Button::new("Clear all"))
+                        .clicked()
+                    {
+                        fixes.extend(self.problems.iter().map(|problem| (*problem, None)));
+                    }
+                    let remap_all = egui::Button::new("Remap all to selected ref");
+                    if ui
+                        .add_enabled(
+                            !self.problems.is_empty() && self.selected_ref.is_some(),
+                            remap_all,
+                        )
+                        .clicked()
+                    {
+                        fixes.extend(
+                            self.problems
+                                .iter()
+                                .map(|problem| (*problem, self.selected_ref)),
+                        );
+                    }
+                });
+                ui.separator();
+                if self.problems.is_empty() {
+                    ui.label("No broken refs");
+                    return;
+                }
+                ui.label(format!("{} broken refs", self.problems.len()));
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .show(ui, |ui| {
+                        egui::Grid::new("validation_problems")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for problem in &self.problems {
+                                    let link = ui.link(format!(
+                                        "Frame {} cell ({}, {})",
+                                        problem.frame + 1,
+                                        problem.cell.0,
+                                        problem.cell.1
+                                    ));
+                                    if link.clicked() && problem.frame < self.ref_matrix.len() {
+                                        self.current_frame = problem.frame;
+                                    }
+                                    ui.label(format!(
+                                        "ref ({}, {}): {}",
+                                        problem.pos.0,
+                                        problem.pos.1,
+                                        problem.kind.describe()
+                                    ));
+                                    if ui.button("Clear").clicked() {
+                                        fixes.push((*problem, None));
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            self.selected_ref.is_some(),
+                                            egui::Button::new("Remap"),
+                                        )
+                                        .clicked()
+                                    {
+                                        fixes.push((*problem, self.selected_ref));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        if !fixes.is_empty() {
+            for (problem, replacement) in fixes {
+                self.fix_problem(problem, replacement);
+            }
+            self.refresh_problems();
+        }
+        self.show_validation = open;
+    }
+}
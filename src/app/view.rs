@@ -0,0 +1,83 @@
+use egui::{vec2, Rect, Vec2};
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 16.;
+
+/// Zoom and pan of a panel drawing a grid of cells (the canvas or the reference)
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PanelView {
+    pub zoom: f32,
+    /// Offset of the first cell from the top-left of the panel
+    pub pan: Vec2,
+    #[serde(skip)]
+    panning: bool,
+}
+
+impl Default for PanelView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.,
+            pan: Vec2::ZERO,
+            panning: false,
+        }
+    }
+}
+
+impl PanelView {
+    /// Side of a cell at zoom 1, in points
+    pub const BASE_CELL_SIZE: f32 = 16.;
+
+    pub fn cell_size(&self) -> f32 {
+        Self::BASE_CELL_SIZE * self.zoom
+    }
+
+    /// Font size for the ref numbers drawn on cells, `None` when too small to read
+    pub fn label_size(&self) -> Option<f32> {
+        let size = (self.cell_size() * 0.4).min(14.);
+        (size >= 6.).then_some(size)
+    }
+
+    /// Zooms so a `size` grid fills the panel `rect`, centered
+    pub fn fit(&mut self, rect: Rect, size: (usize, usize)) {
+        if size.0 == 0 || size.1 == 0 || !rect.is_positive() {
+            return;
+        }
+        let grid = vec2(size.0 as f32, size.1 as f32) * Self::BASE_CELL_SIZE;
+        self.zoom = (rect.width() / grid.x)
+            .min(rect.height() / grid.y)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan = (rect.size() - grid * self.zoom) / 2.;
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.;
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Mouse wheel zooms around the pointer, middle drag pans. `hovered` is false while the
+    /// pointer isn't over the panel's `rect` or something covers it.
+    pub fn handle_input(&mut self, ctx: &egui::Context, rect: Rect, hovered: bool) {
+        let Some(pointer) = ctx.input(|i| i.pointer.latest_pos()) else {
+            return;
+        };
+        if hovered {
+            let factor = ctx.input(|i| i.zoom_delta() * (i.smooth_scroll_delta.y / 200.).exp());
+            if factor != 1. {
+                let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+                let anchor = pointer - rect.min;
+                self.pan = anchor - (anchor - self.pan) * (zoom / self.zoom);
+                self.zoom = zoom;
+            }
+        }
+        if ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Middle)) {
+            self.panning = hovered;
+        }
+        if !ctx.input(|i| i.pointer.middle_down()) {
+            self.panning = false;
+        }
+        if self.panning {
+            self.pan += ctx.input(|i| i.pointer.delta());
+        }
+    }
+}
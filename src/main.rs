@@ -8,7 +8,7 @@ fn main() -> eframe::Result {
 
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1000.0, 300.])
+            .with_inner_size([1000.0, 420.])
             //.with_resizable(false)
             .with_icon(
                 // NOTE: Adding an icon is optional